@@ -1,10 +1,15 @@
-use std::{net::UdpSocket, time::Duration};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    net::{Ipv6Addr, UdpSocket},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
 
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
-use ureq::{Agent, AgentBuilder};
+use ureq::{Agent, AgentBuilder, Request, Response};
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
 pub struct Bulb {
     #[serde(default = "default_brightness")]
     pub brightness: f32, // range: 0..1
@@ -16,6 +21,124 @@ pub struct Bulb {
     pub enabled: u8, // api uses int instead of bool
 }
 
+impl Bulb {
+    /// Returns red, green and blue channels of color, invalid channels are
+    /// treated as fully saturated, like bulbs firmware does.
+    pub fn rgb(&self) -> (u8, u8, u8) {
+        let hex = self.color.strip_prefix('#').unwrap_or(&self.color);
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+                .unwrap_or(u8::MAX)
+        };
+        (channel(0), channel(2), channel(4))
+    }
+
+    /// Describes how `new` state differs from this one, e.g.
+    /// `power on -> off, brightness 40% -> 50%`. Returns `None` if it doesn't.
+    pub fn changes(&self, new: &Self) -> Option<String> {
+        let power = |b: &Self| if b.enabled == 1 { "on" } else { "off" };
+        let mut changes = Vec::new();
+        if self.enabled != new.enabled {
+            changes.push(format!("power {} -> {}", power(self), power(new)));
+        }
+        if !self.color.eq_ignore_ascii_case(&new.color) {
+            changes.push(format!("color {} -> {}", self.color, new.color));
+        }
+        if (self.brightness - new.brightness).abs() > f32::EPSILON {
+            changes.push(format!(
+                "brightness {:.0}% -> {:.0}%",
+                self.brightness * 100.0,
+                new.brightness * 100.0
+            ));
+        }
+        (!changes.is_empty()).then(|| changes.join(", "))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ErrorKind {
+    /// Device responded with error status code.
+    Status,
+    /// Device couldn't be reached or connection broke.
+    Transport,
+    /// Device responded with something that isn't valid status.
+    Parse,
+}
+
+impl ErrorKind {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Status => "status",
+            Self::Transport => "transport",
+            Self::Parse => "parse",
+        }
+    }
+}
+
+/// Statistics of requests made to device during session.
+#[derive(Debug, Default)]
+pub struct Stats {
+    /// Whether device responded to last request.
+    pub up: bool,
+    pub last_seen: Option<SystemTime>,
+    /// Duration of last request.
+    pub latency: Option<Duration>,
+    /// Durations of recent requests, oldest first.
+    pub latencies: VecDeque<Duration>,
+    pub errors: BTreeMap<ErrorKind, u64>,
+    /// Body of last `/led` response.
+    pub last_response: Option<String>,
+    /// Brightness in percent after each change of state, 0 when off.
+    pub levels: VecDeque<u64>,
+}
+
+/// How many recent latencies and levels are kept.
+const STATS_HISTORY: usize = 100;
+
+impl Stats {
+    fn record_error(&mut self, kind: ErrorKind) {
+        *self.errors.entry(kind).or_default() += 1;
+    }
+
+    fn record_latency(&mut self, latency: Duration) {
+        self.latency = Some(latency);
+        if self.latencies.len() == STATS_HISTORY {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(latency);
+    }
+
+    /// Records level of `bulb` if it changed.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn record_level(&mut self, bulb: &Bulb) {
+        let level = if bulb.enabled == 1 {
+            (bulb.brightness.clamp(0.0, 1.0) * 100.0).round() as u64
+        } else {
+            0
+        };
+        if self.levels.back() == Some(&level) {
+            return;
+        }
+        if self.levels.len() == STATS_HISTORY {
+            self.levels.pop_front();
+        }
+        self.levels.push_back(level);
+    }
+
+    /// Returns average of recent latencies.
+    pub fn average_latency(&self) -> Option<Duration> {
+        let count = u32::try_from(self.latencies.len())
+            .ok()
+            .filter(|&c| c > 0)?;
+        Some(self.latencies.iter().sum::<Duration>() / count)
+    }
+
+    pub fn error_count(&self) -> u64 {
+        self.errors.values().sum()
+    }
+}
+
 #[derive(Debug, Default, Deserialize, Serialize)]
 pub struct Device {
     #[serde(flatten)]
@@ -28,12 +151,30 @@ pub struct Device {
 
     #[serde(default = "always_true")]
     pub selected: bool,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+
+    /// Whether device is filtered out of device list, hidden devices are
+    /// left out of actions on selected devices.
+    #[serde(skip)]
+    pub hidden: bool,
+
+    #[serde(skip)]
+    pub stats: Stats,
 }
 
-const fn always_true() -> bool {
+pub const fn always_true() -> bool {
     true
 }
 
+/// Tells serde to skip flag that is at its [`always_true`] default.
+// signature is dictated by serde
+#[allow(clippy::trivially_copy_pass_by_ref)]
+pub const fn is_true(flag: &bool) -> bool {
+    *flag
+}
+
 const fn default_brightness() -> f32 {
     1.0
 }
@@ -52,31 +193,72 @@ impl Device {
         }
     }
 
+    /// Returns URL of `path` on device. Bare IPv6 address is put in brackets.
+    pub fn url(&self, path: &str) -> String {
+        if self.ip.parse::<Ipv6Addr>().is_ok() {
+            format!("http://[{}]{path}", self.ip)
+        } else {
+            format!("http://{}{path}", self.ip)
+        }
+    }
+
+    /// Returns true if device is selected and not hidden.
+    pub const fn is_active(&self) -> bool {
+        self.selected && !self.hidden
+    }
+
+    /// Returns true if every word of `term` fuzzy matches name, address or
+    /// one of groups, ignoring ASCII case.
+    pub fn matches(&self, term: &str) -> bool {
+        term.split_whitespace().all(|word| {
+            std::iter::once(&self.name)
+                .chain(std::iter::once(&self.ip))
+                .chain(&self.groups)
+                .any(|field| fuzzy_match(field, word))
+        })
+    }
+
+    /// Sends request and records its outcome in device stats.
+    fn call(&mut self, request: Request) -> Result<Response> {
+        let start = Instant::now();
+        let res = request.call();
+        self.stats.record_latency(start.elapsed());
+        self.stats.up = !matches!(res, Err(ureq::Error::Transport(_)));
+        if self.stats.up {
+            self.stats.last_seen = Some(SystemTime::now());
+        }
+        res.map_err(|e| {
+            self.stats.record_error(match e {
+                ureq::Error::Status(..) => ErrorKind::Status,
+                ureq::Error::Transport(_) => ErrorKind::Transport,
+            });
+            with_body(e)
+        })
+    }
+
     pub fn get_status(&mut self, agent: &Agent) -> Result<String> {
-        let resp = agent
-            .get(format!("http://{}/led", self.ip).as_str())
-            .call()
-            .map_err(with_body)?
-            .into_string()?;
-        self.bulb = serde_json::from_str(&resp)?;
-        Ok(format!("{}: {}", self.ip, resp))
+        let resp = self
+            .call(agent.get(self.url("/led").as_str()))?
+            .into_string()
+            .inspect_err(|_| self.stats.record_error(ErrorKind::Transport))?;
+        self.stats.last_response = Some(resp.clone());
+        self.bulb = serde_json::from_str(&resp)
+            .inspect_err(|_| self.stats.record_error(ErrorKind::Parse))?;
+        self.stats.record_level(&self.bulb);
+        Ok(resp)
     }
 
     pub fn on(&mut self, agent: &Agent) -> Result<()> {
-        agent
-            .put(format!("http://{}/led/on", self.ip).as_str())
-            .call()
-            .map_err(with_body)?;
+        self.call(agent.put(self.url("/led/on").as_str()))?;
         self.bulb.enabled = 1;
+        self.stats.record_level(&self.bulb);
         Ok(())
     }
 
     pub fn off(&mut self, agent: &Agent) -> Result<()> {
-        agent
-            .put(format!("http://{}/led/off", self.ip).as_str())
-            .call()
-            .map_err(with_body)?;
+        self.call(agent.put(self.url("/led/off").as_str()))?;
         self.bulb.enabled = 0;
+        self.stats.record_level(&self.bulb);
         Ok(())
     }
 
@@ -90,36 +272,34 @@ impl Device {
 
     pub fn set_color(&mut self, agent: &Agent, color: &str) -> Result<()> {
         let color = color.strip_prefix('#').unwrap_or(color);
-        agent
-            .put(format!("http://{}/led/color/{}", self.ip, color).as_str())
-            .call()
-            .map_err(with_body)?;
+        self.call(agent.put(self.url(&format!("/led/color/{color}")).as_str()))?;
         self.bulb.color = "#".to_owned() + color;
         Ok(())
     }
 
     pub fn set_brightness(&mut self, agent: &Agent, brightness: f32) -> Result<()> {
-        agent
-            .put(format!("http://{}/led/brightness/{}", self.ip, brightness).as_str())
-            .call()
-            .map_err(with_body)?;
+        self.call(agent.put(self.url(&format!("/led/brightness/{brightness}")).as_str()))?;
         self.bulb.brightness = brightness;
+        self.stats.record_level(&self.bulb);
         Ok(())
     }
-}
 
-impl std::fmt::Display for Device {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(
-            f,
-            "{} {:16} {:16} {:3} {:4} {:7}",
-            if self.selected { ">" } else { " " },
-            self.ip,
-            self.name,
-            if self.bulb.enabled == 1 { "ON" } else { "OFF" },
-            self.bulb.brightness,
-            self.bulb.color,
-        )
+    /// Sends only requests needed to bring LED to given state.
+    pub fn apply(&mut self, agent: &Agent, state: &Bulb) -> Result<()> {
+        if !self.bulb.color.eq_ignore_ascii_case(&state.color) {
+            self.set_color(agent, &state.color)?;
+        }
+        if (self.bulb.brightness - state.brightness).abs() > f32::EPSILON {
+            self.set_brightness(agent, state.brightness)?;
+        }
+        if self.bulb.enabled != state.enabled {
+            if state.enabled == 1 {
+                self.on(agent)?;
+            } else {
+                self.off(agent)?;
+            }
+        }
+        Ok(())
     }
 }
 
@@ -128,7 +308,7 @@ pub struct Devices {
     #[serde(skip, default = "default_agent")]
     agent: Agent,
 
-    #[serde(rename = "bulb")]
+    #[serde(default, rename = "bulb")]
     pub bulbs: Vec<Device>,
 }
 
@@ -154,10 +334,42 @@ impl Devices {
         Ok(resp)
     }
 
+    /// Moves device to new address and fetches its status from there.
+    /// Statistics of old address are dropped.
+    pub fn readdress(&mut self, index: usize, ip: String) -> Result<String> {
+        let dev = &mut self.bulbs[index];
+        dev.ip = ip;
+        dev.stats = Stats::default();
+        dev.get_status(&self.agent)
+    }
+
+    /// Refreshes status of all selected devices in parallel, even if some of
+    /// them fail. Returns address of each device with its response.
+    pub fn refresh(&mut self) -> Vec<(String, Result<String>)> {
+        let agent = &self.agent;
+        thread::scope(|s| {
+            let handles: Vec<_> = self
+                .bulbs
+                .iter_mut()
+                .filter(|d| d.is_active())
+                .map(|dev| (dev.ip.clone(), s.spawn(|| dev.get_status(agent))))
+                .collect();
+            handles
+                .into_iter()
+                .map(|(ip, h)| {
+                    let res = h
+                        .join()
+                        .unwrap_or_else(|_| Err(anyhow!("status request panicked")));
+                    (ip, res)
+                })
+                .collect()
+        })
+    }
+
     pub fn get_status(&mut self) -> Result<Option<String>> {
         let mut resp = String::new();
         for i in 0..self.bulbs.len() {
-            if self.bulbs[i].selected {
+            if self.bulbs[i].is_active() {
                 resp.push_str(&self.bulbs[i].get_status(&self.agent)?);
             }
         }
@@ -170,7 +382,7 @@ impl Devices {
 
     pub fn on(&mut self) -> Result<()> {
         for i in 0..self.bulbs.len() {
-            if self.bulbs[i].selected {
+            if self.bulbs[i].is_active() {
                 self.bulbs[i].on(&self.agent)?;
             }
         }
@@ -179,7 +391,7 @@ impl Devices {
 
     pub fn off(&mut self) -> Result<()> {
         for i in 0..self.bulbs.len() {
-            if self.bulbs[i].selected {
+            if self.bulbs[i].is_active() {
                 self.bulbs[i].off(&self.agent)?;
             }
         }
@@ -189,7 +401,7 @@ impl Devices {
     pub fn toggle(&mut self) -> Result<()> {
         let mut first_is_enabled = 0;
         for i in 0..self.bulbs.len() {
-            if self.bulbs[i].selected {
+            if self.bulbs[i].is_active() {
                 first_is_enabled = self.bulbs[i].bulb.enabled;
                 break;
             }
@@ -205,9 +417,37 @@ impl Devices {
         self.bulbs[index].toggle(&self.agent)
     }
 
+    /// Returns state of selected devices.
+    pub fn snapshot(&self) -> Vec<(String, Bulb)> {
+        self.bulbs
+            .iter()
+            .filter(|d| d.is_active())
+            .map(|d| (d.ip.clone(), d.bulb.clone()))
+            .collect()
+    }
+
+    /// Brings devices to given state, devices that were removed are skipped.
+    /// Failure of one device doesn't stop others from being restored, error
+    /// lists all devices that failed.
+    pub fn restore(&mut self, states: &[(String, Bulb)]) -> Result<()> {
+        let mut errors = Vec::new();
+        for (ip, state) in states {
+            if let Some(dev) = self.bulbs.iter_mut().find(|d| d.ip == *ip) {
+                if let Err(e) = dev.apply(&self.agent, state) {
+                    errors.push(e.to_string());
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(errors.join("; ")))
+        }
+    }
+
     pub fn set_color(&mut self, color: &str) -> Result<()> {
         for i in 0..self.bulbs.len() {
-            if self.bulbs[i].selected {
+            if self.bulbs[i].is_active() {
                 self.bulbs[i].set_color(&self.agent, color)?;
             }
         }
@@ -216,7 +456,7 @@ impl Devices {
 
     pub fn set_brightness(&mut self, brightness: f32) -> Result<()> {
         for i in 0..self.bulbs.len() {
-            if self.bulbs[i].selected {
+            if self.bulbs[i].is_active() {
                 self.bulbs[i].set_brightness(&self.agent, brightness)?;
             }
         }
@@ -224,6 +464,45 @@ impl Devices {
     }
 }
 
+/// Bulk change of which devices are selected.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Selection {
+    All,
+    None,
+    Invert,
+    /// Devices that are on.
+    On,
+    /// Devices that are off.
+    Off,
+    /// Devices left visible by filter.
+    Filtered,
+}
+
+impl Selection {
+    /// Selects devices picked by selector and deselects the rest.
+    pub fn apply(self, devices: &mut Devices) {
+        for dev in &mut devices.bulbs {
+            dev.selected = match self {
+                Self::All => true,
+                Self::None => false,
+                Self::Invert => !dev.selected,
+                Self::On => dev.bulb.enabled == 1,
+                Self::Off => dev.bulb.enabled != 1,
+                Self::Filtered => !dev.hidden,
+            };
+        }
+    }
+}
+
+/// Returns true if characters of `needle` appear in `haystack` in order,
+/// ignoring ASCII case.
+fn fuzzy_match(haystack: &str, needle: &str) -> bool {
+    let mut chars = haystack.chars();
+    needle
+        .chars()
+        .all(|n| chars.any(|h| h.eq_ignore_ascii_case(&n)))
+}
+
 /// Converts `ureq::Error` to `anyhow::Error` but with added response body.
 /// Needed because donwcasting later is not possible and anyhow by default
 /// doesn't display body.
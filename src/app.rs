@@ -1,14 +1,95 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use crossterm::event::KeyEvent;
+use ratatui::{
+    layout::{Position, Rect},
+    widgets::TableState,
+};
+use serde::{Deserialize, Serialize};
 use std::io;
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    net::Ipv6Addr,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
 
-use crate::api::{self, Device, Devices};
+use crate::api::Selection;
+use crate::api::{self, Bulb, Device, Devices};
+use crate::color::Hsv;
+use crate::command::{self, Command, Power};
+use crate::ctl;
+use crate::file;
+use crate::history::{History, Snapshot};
+use crate::input::{self, Input};
+use crate::keys::{self, Keymap, KeysConfig};
+use crate::logs::{Entry, Level, Log};
+use crate::merge::{self, Merge};
+use crate::table::TableConfig;
+use crate::theme::{self, Theme, ThemeConfig};
 
+#[derive(Clone, Copy)]
 pub enum CurrentWidget {
     Devices,
+    DeviceSearch,
     Logs,
+    LogSearch,
     AddDevice,
     DeviceSettings,
+    /// Command line, opened from device list or logs.
+    Command,
+    /// Prompt confirming removal of current device.
+    ConfirmRemove,
+}
+
+impl CurrentWidget {
+    /// Returns context of key bindings active in widget.
+    pub const fn keys(self) -> keys::Context {
+        match self {
+            Self::Devices => keys::Context::Devices,
+            Self::Logs => keys::Context::Logs,
+            Self::DeviceSearch | Self::LogSearch => keys::Context::Search,
+            Self::AddDevice => keys::Context::AddDevice,
+            Self::DeviceSettings => keys::Context::Settings,
+            Self::Command => keys::Context::Command,
+            Self::ConfirmRemove => keys::Context::Confirm,
+        }
+    }
+}
+
+/// Degrees of hue one color picker step moves.
+const HUE_STEP: f32 = 5.0;
+/// Saturation or value one color picker step moves.
+const SHADE_STEP: f32 = 0.05;
+
+/// How long edits must pause before live preview sends them to devices.
+const PREVIEW_DELAY: Duration = Duration::from_millis(200);
+
+/// Brightness one nudge from device list changes.
+const BRIGHTNESS_STEP: f32 = 0.1;
+/// Degrees of hue one nudge from device list rotates.
+const NUDGE_HUE_STEP: f32 = 15.0;
+/// How long nudges must pause before they are sent, so that key repeats
+/// result in one request per device.
+const NUDGE_DELAY: Duration = Duration::from_millis(150);
+
+/// Number of previous versions of config file kept by default.
+const DEFAULT_BACKUPS: usize = 3;
+
+/// How often config file is checked for changes made by others.
+const CONFIG_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Part of UI that reacts to mouse. Devices are referred to by address, as
+/// device list may change between drawing and click.
+#[derive(Clone)]
+pub enum Target {
+    DeviceList,
+    Device(String),
+    /// ON/OFF column of device.
+    Power(String),
+    /// Color swatch of device.
+    Color(String),
+    Logs,
+    Popup,
 }
 
 pub enum CurrentlyAdding {
@@ -18,147 +99,1099 @@ pub enum CurrentlyAdding {
 
 pub enum CurrentlySetting {
     Color,
+    /// Hue bar of color picker.
+    Hue,
+    /// Saturation/value grid of color picker.
+    Shade,
     Brightness,
 }
 
+/// Contents of config file.
+#[derive(Deserialize, Serialize)]
+pub struct Config {
+    #[serde(flatten)]
+    pub devices: Devices,
+
+    #[serde(default, skip_serializing_if = "KeysConfig::is_empty")]
+    pub keys: KeysConfig,
+
+    #[serde(default, skip_serializing_if = "ThemeConfig::is_default")]
+    pub theme: ThemeConfig,
+
+    #[serde(default, skip_serializing_if = "TableConfig::is_default")]
+    pub table: TableConfig,
+
+    /// Whether removal of device must be confirmed.
+    #[serde(default = "api::always_true", skip_serializing_if = "api::is_true")]
+    pub confirm_remove: bool,
+
+    /// Number of previous versions of config file kept, `DEFAULT_BACKUPS` if
+    /// not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backups: Option<usize>,
+}
+
+/// Borrowed [`Config`], for writing it without moving devices out of [`App`].
+#[derive(Serialize)]
+struct ConfigRef<'a> {
+    #[serde(flatten)]
+    devices: &'a Devices,
+
+    #[serde(skip_serializing_if = "KeysConfig::is_empty")]
+    keys: &'a KeysConfig,
+
+    #[serde(skip_serializing_if = "ThemeConfig::is_default")]
+    theme: &'a ThemeConfig,
+
+    #[serde(skip_serializing_if = "TableConfig::is_default")]
+    table: &'a TableConfig,
+
+    #[serde(skip_serializing_if = "api::is_true")]
+    confirm_remove: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    backups: &'a Option<usize>,
+}
+
+#[allow(clippy::struct_excessive_bools)]
 pub struct App {
     pub devices: Devices,
-    pub logs: Vec<String>,
+    keys_config: KeysConfig,
+    pub keymap: Keymap,
+    theme_config: ThemeConfig,
+    pub theme: Theme,
+    /// Columns and sorting of device list, written back to config.
+    pub table: TableConfig,
+    /// Whether removal of device must be confirmed, written back to config.
+    confirm_remove: bool,
+    backups: Option<usize>,
+    /// Devices removed this session with address of device that followed
+    /// them, last removed at the end.
+    trash: Vec<(Option<String>, Device)>,
+    pub logs: Log,
     config_path: PathBuf,
+    /// Config as last read or written, base of merging changes made on disk.
+    config_saved: String,
+    /// Modification time of config file when it was last read or written.
+    config_modified: Option<SystemTime>,
+    config_checked: Instant,
+    /// Lock of config file, `None` if it couldn't be taken.
+    config_lock: Option<file::Lock>,
+    /// Whether config is locked by another instance, so it isn't written.
+    pub read_only: bool,
+    control: Option<ctl::Server>,
+    history: History,
 
     pub current_device_index: usize,
+    /// Scroll state of device list, updated on every draw.
+    pub device_list: TableState,
+    /// Whether detail panel of current device is shown.
+    pub show_details: bool,
+    /// Whether help overlay is shown, on top of current widget.
+    pub show_help: bool,
+    /// Number of lines help overlay is scrolled down, clamped on draw.
+    pub help_offset: u16,
+    /// Number of lines that fit in help overlay, updated on every draw.
+    pub help_height: u16,
+    /// Fuzzy filter of device list, devices that don't match are hidden.
+    pub device_filter: String,
     pub current_widget: CurrentWidget,
     pub currently_adding: Option<CurrentlyAdding>,
+    /// Address of device edited in add device popup, `None` when adding.
+    pub editing: Option<String>,
     pub currently_setting: Option<CurrentlySetting>,
 
+    /// Areas of UI that react to mouse, updated on every draw. Later ones
+    /// are on top.
+    pub targets: Vec<(Rect, Target)>,
+
     pub log_horizontal_offset: u16,
-    pub color_input: String,
-    pub brightness_input: String,
-    pub ip_input: String,
-    pub name_input: String,
+    /// Id of the last entry shown in log view when it is scrolled up, so that
+    /// view stays put as new entries arrive. `None` follows the newest entry.
+    pub log_anchor: Option<u64>,
+    /// Number of log lines that fit in log view, updated on every draw.
+    pub log_height: usize,
+    pub log_search: String,
+    /// Least severe level of displayed entries.
+    pub log_level: Level,
+    /// Address of device whose entries are displayed, all if `None`.
+    pub log_device: Option<String>,
+    pub color_input: Input,
+    /// Color picker position, kept in sync with `color_input`.
+    pub picker: Hsv,
+    pub brightness_input: Input,
+    /// Whether edits in device settings are sent to devices before applying.
+    pub live_preview: bool,
+    /// When pending live preview should be sent.
+    preview_due: Option<Instant>,
+    /// State of devices edited in device settings when they were opened,
+    /// settings are applied only to these devices.
+    settings_before: Snapshot,
+    /// State nudged devices should be brought to, not sent yet.
+    nudged: Snapshot,
+    /// When nudged state should be sent.
+    nudge_due: Option<Instant>,
+    pub ip_input: Input,
+    pub name_input: Input,
+    pub command_input: String,
+    /// Candidates of last ambiguous completion in command line.
+    pub command_hint: Vec<String>,
+    /// Widget focused before command line was opened.
+    command_return: CurrentWidget,
+    commands: command::History,
+    /// Position in command history while browsing it, `None` when editing
+    /// new line.
+    command_pos: Option<usize>,
 }
 
 macro_rules! log {
-    ($app:expr, $line:expr) => {{
-        $app.logs.push($line);
+    ($app:expr, $level:ident, $line:expr) => {{
+        $app.logs.push(Entry::new(Level::$level, None, $line));
+    }};
+    ($app:expr, $level:ident, $device:expr, $line:expr) => {{
+        $app.logs
+            .push(Entry::new(Level::$level, Some($device), $line));
     }};
 }
 
 impl App {
-    pub fn new(config: Devices, path: PathBuf) -> Self {
-        Self {
-            devices: config,
-            logs: Vec::default(),
+    pub fn new(config: Config, path: PathBuf, keymap: Keymap) -> Self {
+        let mut app = Self {
+            devices: config.devices,
+            keys_config: config.keys,
+            keymap,
+            theme: Theme::new(&config.theme, theme::no_color()),
+            theme_config: config.theme,
+            table: config.table,
+            confirm_remove: config.confirm_remove,
+            backups: config.backups,
+            trash: Vec::new(),
+            logs: Log::new(),
+            config_modified: modified(&path),
             config_path: path,
+            config_saved: String::new(),
+            config_checked: Instant::now(),
+            config_lock: None,
+            read_only: false,
+            control: None,
+            history: History::default(),
 
             current_device_index: 0,
+            device_list: TableState::default(),
+            show_details: false,
+            show_help: false,
+            help_offset: 0,
+            help_height: 0,
+            device_filter: String::new(),
             current_widget: CurrentWidget::Devices,
             currently_adding: None,
+            editing: None,
             currently_setting: None,
 
+            targets: Vec::new(),
+
             log_horizontal_offset: 0,
-            color_input: String::new(),
-            brightness_input: String::new(),
-            ip_input: String::new(),
-            name_input: String::new(),
+            log_anchor: None,
+            log_height: 0,
+            log_search: String::new(),
+            log_level: Level::Debug,
+            log_device: None,
+            color_input: Input::new(check_color),
+            picker: Hsv::default(),
+            brightness_input: Input::new(|s| command::parse_brightness(s).map(drop)),
+            live_preview: false,
+            preview_due: None,
+            settings_before: Vec::new(),
+            nudged: Vec::new(),
+            nudge_due: None,
+            ip_input: Input::new(check_address),
+            name_input: Input::default(),
+            command_input: String::new(),
+            command_hint: Vec::new(),
+            command_return: CurrentWidget::Devices,
+            commands: command::History::default(),
+            command_pos: None,
+        };
+        app.config_saved = app.config_string().unwrap_or_default();
+        app
+    }
+
+    /// Starts mirroring logs to file.
+    pub fn open_log(&mut self, path: &Path) {
+        if let Err(e) = self.logs.open(path) {
+            log!(
+                self,
+                Warn,
+                format!("failed to open log file {}: {e}", path.to_string_lossy())
+            );
+        }
+    }
+
+    /// Loads command line history, new lines are appended to file.
+    pub fn open_history(&mut self, path: &Path) {
+        match command::History::open(path) {
+            Ok(v) => self.commands = v,
+            Err(e) => log!(
+                self,
+                Warn,
+                format!(
+                    "failed to open history file {}: {e}",
+                    path.to_string_lossy()
+                )
+            ),
+        }
+    }
+
+    /// Starts listening for commands from `bulbs-tui cli` invocations.
+    pub fn listen(&mut self, socket: &Path) {
+        match ctl::Server::bind(socket) {
+            Ok(Some(v)) => self.control = Some(v),
+            Ok(None) => log!(
+                self,
+                Warn,
+                format!(
+                    "Another instance is listening on {}, changes made here may be overwritten",
+                    socket.to_string_lossy()
+                )
+            ),
+            Err(e) => log!(
+                self,
+                Error,
+                format!("failed to listen on {}: {e}", socket.to_string_lossy())
+            ),
+        }
+    }
+
+    /// Runs commands forwarded through control socket.
+    pub fn handle_control(&mut self) {
+        while let Some((conn, req)) = self.control.as_ref().and_then(ctl::Server::accept) {
+            let res = match req {
+                Ok(cmd) if cmd.is_forwardable() => {
+                    // remote commands don't know about filter of device list
+                    for dev in &mut self.devices.bulbs {
+                        dev.hidden = false;
+                    }
+                    let res = cmd.run(&mut self.devices);
+                    self.filter_devices();
+                    res
+                }
+                Ok(_) => Err(anyhow!("command can't be run by TUI instance")),
+                Err(e) => Err(e),
+            };
+            match &res {
+                Ok(Some(v)) => log!(self, Info, v.clone()),
+                Ok(None) => (),
+                Err(e) => log!(self, Error, format!("remote command failed: {e}")),
+            }
+            if let Err(e) = conn.respond(&res) {
+                log!(
+                    self,
+                    Error,
+                    format!("failed to respond to remote command: {e}")
+                );
+            }
         }
     }
 
-    pub fn toggle_adding_field(&mut self) {
+    fn target_at(&self, column: u16, row: u16) -> Option<Target> {
+        self.targets
+            .iter()
+            .rev()
+            .find(|(area, _)| area.contains(Position { x: column, y: row }))
+            .map(|(_, target)| target.clone())
+    }
+
+    /// Closes popup without applying it, color and brightness changed by
+    /// live preview are brought back to previous values. Other changes made
+    /// meanwhile, like power, are kept.
+    pub fn close_popup(&mut self) {
+        if matches!(self.current_widget, CurrentWidget::DeviceSettings) {
+            self.preview_due = None;
+            let before: Snapshot = std::mem::take(&mut self.settings_before)
+                .into_iter()
+                .filter_map(|(ip, old)| {
+                    let dev = self.devices.bulbs.iter().find(|d| d.ip == ip)?;
+                    let bulb = Bulb {
+                        color: old.color,
+                        brightness: old.brightness,
+                        ..dev.bulb.clone()
+                    };
+                    Some((ip, bulb))
+                })
+                .collect();
+            if let Err(e) = self.devices.restore(&before) {
+                log!(self, Error, format!("failed to restore devices: {e}"));
+            }
+        }
+        if self.editing.take().is_some() {
+            self.ip_input.clear();
+            self.name_input.clear();
+        }
+        self.current_widget = CurrentWidget::Devices;
+        self.currently_adding = None;
+        self.currently_setting = None;
+    }
+
+    pub fn click(&mut self, column: u16, row: u16) {
+        if self.show_help {
+            self.show_help = false;
+            return;
+        }
+        let target = self.target_at(column, row);
+        match self.current_widget {
+            CurrentWidget::AddDevice
+            | CurrentWidget::DeviceSettings
+            | CurrentWidget::ConfirmRemove => {
+                if !matches!(target, Some(Target::Popup)) {
+                    self.close_popup();
+                }
+                return;
+            }
+            CurrentWidget::Devices
+            | CurrentWidget::DeviceSearch
+            | CurrentWidget::Logs
+            | CurrentWidget::LogSearch
+            | CurrentWidget::Command => (),
+        }
+        match target {
+            // device clicked may be gone if list changed since it was drawn
+            Some(Target::Device(ip)) => {
+                let Some(i) = self.device_index(&ip) else {
+                    return;
+                };
+                self.current_widget = CurrentWidget::Devices;
+                self.current_device_index = i;
+            }
+            Some(Target::Power(ip)) => {
+                let Some(i) = self.device_index(&ip) else {
+                    return;
+                };
+                self.current_widget = CurrentWidget::Devices;
+                self.current_device_index = i;
+                self.toggle_current();
+            }
+            Some(Target::Color(ip)) => {
+                let Some(i) = self.device_index(&ip) else {
+                    return;
+                };
+                self.current_widget = CurrentWidget::Devices;
+                self.current_device_index = i;
+                let bulb = self.devices.bulbs[i].bulb.clone();
+                self.open_settings_of(vec![(ip, bulb)]);
+            }
+            Some(Target::DeviceList) => self.current_widget = CurrentWidget::Devices,
+            Some(Target::Logs) => self.current_widget = CurrentWidget::Logs,
+            Some(Target::Popup) | None => (),
+        }
+    }
+
+    /// Scrolls part of UI under mouse.
+    pub fn scroll(&mut self, column: u16, row: u16, up: bool) {
+        if self.show_help {
+            self.help_offset = if up {
+                self.help_offset.saturating_sub(3)
+            } else {
+                self.help_offset.saturating_add(3)
+            };
+            return;
+        }
+        match self.target_at(column, row) {
+            Some(Target::Logs) if up => self.scroll_logs_up(3),
+            Some(Target::Logs) => self.scroll_logs_down(3),
+            Some(Target::DeviceList | Target::Device(_) | Target::Power(_) | Target::Color(_))
+                if matches!(
+                    self.current_widget,
+                    CurrentWidget::Devices | CurrentWidget::Logs
+                ) =>
+            {
+                if up {
+                    self.prev_device();
+                } else {
+                    self.next_device();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub const fn toggle_adding_field(&mut self) {
         if let Some(edit_mode) = &self.currently_adding {
             match edit_mode {
                 CurrentlyAdding::IP => self.currently_adding = Some(CurrentlyAdding::Name),
                 CurrentlyAdding::Name => self.currently_adding = Some(CurrentlyAdding::IP),
-            };
+            }
         } else {
             self.currently_adding = Some(CurrentlyAdding::IP);
         }
     }
 
-    pub fn toggle_settings_field(&mut self) {
+    pub const fn toggle_settings_field(&mut self) {
         if let Some(edit_mode) = &self.currently_setting {
             match edit_mode {
-                CurrentlySetting::Color => {
+                CurrentlySetting::Color => self.currently_setting = Some(CurrentlySetting::Hue),
+                CurrentlySetting::Hue => self.currently_setting = Some(CurrentlySetting::Shade),
+                CurrentlySetting::Shade => {
                     self.currently_setting = Some(CurrentlySetting::Brightness);
                 }
                 CurrentlySetting::Brightness => {
                     self.currently_setting = Some(CurrentlySetting::Color);
                 }
-            };
+            }
         } else {
             self.currently_setting = Some(CurrentlySetting::Color);
         }
     }
 
+    /// Returns context of key bindings active in focused widget.
+    pub const fn keys(&self) -> keys::Context {
+        if self.show_help {
+            return keys::Context::Help;
+        }
+        match (&self.current_widget, &self.currently_setting) {
+            (
+                CurrentWidget::DeviceSettings,
+                Some(CurrentlySetting::Hue | CurrentlySetting::Shade),
+            ) => keys::Context::Picker,
+            (w, _) => w.keys(),
+        }
+    }
+
+    /// Moves color picker cursor by `dx` and `dy` steps. On hue bar vertical
+    /// steps are coarse horizontal ones, on grid `dx` changes saturation and
+    /// `dy` value.
+    pub fn move_picker(&mut self, dx: f32, dy: f32) {
+        let Hsv { h, s, v } = self.picker;
+        self.picker = match self.currently_setting {
+            Some(CurrentlySetting::Hue) => Hsv::new(dy.mul_add(6.0, dx).mul_add(HUE_STEP, h), s, v),
+            Some(CurrentlySetting::Shade) => {
+                Hsv::new(h, dx.mul_add(SHADE_STEP, s), dy.mul_add(SHADE_STEP, v))
+            }
+            _ => return,
+        };
+        self.color_input.set(self.picker.to_hex());
+        self.schedule_preview();
+    }
+
+    /// Moves color picker to typed color, if it is complete.
+    pub fn sync_picker(&mut self) {
+        if let Some(hsv) = Hsv::from_hex(self.color_input.value()) {
+            self.picker = hsv;
+        }
+        self.schedule_preview();
+    }
+
+    pub const fn open_help(&mut self) {
+        self.show_help = true;
+        self.help_offset = 0;
+    }
+
+    pub fn toggle_preview(&mut self) {
+        self.live_preview = !self.live_preview;
+        if self.live_preview {
+            self.schedule_preview();
+        } else {
+            self.preview_due = None;
+        }
+    }
+
+    /// Delays live preview until edits pause.
+    pub fn schedule_preview(&mut self) {
+        if self.live_preview {
+            self.preview_due = Some(Instant::now() + PREVIEW_DELAY);
+        }
+    }
+
+    /// Sends edited color and brightness to edited devices if live preview
+    /// is due. Incomplete inputs are skipped and unchanged values not sent.
+    pub fn update_preview(&mut self) {
+        if self.preview_due.is_none_or(|due| due > Instant::now()) {
+            return;
+        }
+        self.preview_due = None;
+
+        let color = Hsv::from_hex(self.color_input.value()).map(|_| self.color_input.value());
+        let brightness = command::parse_brightness(self.brightness_input.value()).ok();
+        let states = self.settings_state(color, brightness);
+        if let Err(e) = self.devices.restore(&states) {
+            log!(self, Error, format!("failed to preview settings: {e}"));
+        }
+    }
+
+    /// Returns current state of edited devices with color and brightness
+    /// replaced by given ones.
+    fn settings_state(&self, color: Option<&str>, brightness: Option<f32>) -> Snapshot {
+        self.current_state(&self.settings_before)
+            .into_iter()
+            .map(|(ip, mut bulb)| {
+                if let Some(c) = color {
+                    bulb.color = c.to_string();
+                }
+                if let Some(b) = brightness {
+                    bulb.brightness = b;
+                }
+                (ip, bulb)
+            })
+            .collect()
+    }
+
+    /// Opens device settings of selected devices.
     pub fn open_settings(&mut self) {
-        if let Some(first) = self.devices.bulbs.iter().find(|d| d.selected) {
-            self.color_input = first.bulb.color.to_string();
-            self.brightness_input = first.bulb.brightness.to_string();
+        self.open_settings_of(self.devices.snapshot());
+    }
+
+    /// Opens device settings of given devices, inputs are filled from the
+    /// first one.
+    fn open_settings_of(&mut self, devices: Snapshot) {
+        if let Some((_, first)) = devices.first() {
+            self.color_input.set(first.color.clone());
+            self.picker = Hsv::from_hex(&first.color).unwrap_or_default();
+            self.brightness_input.set(first.brightness.to_string());
+            self.settings_before = devices;
             self.current_widget = CurrentWidget::DeviceSettings;
             self.currently_setting = Some(CurrentlySetting::Color);
         }
     }
 
-    pub fn scroll_logs_left(&mut self) {
+    pub const fn scroll_logs_left(&mut self) {
         self.log_horizontal_offset = self.log_horizontal_offset.saturating_sub(4);
     }
-    pub fn scroll_logs_right(&mut self) {
+    pub const fn scroll_logs_right(&mut self) {
         self.log_horizontal_offset = self.log_horizontal_offset.saturating_add(4);
     }
 
+    /// Returns log entries that pass level and device filters.
+    pub fn visible_logs(&self) -> Vec<&Entry> {
+        self.logs
+            .iter()
+            .filter(|e| e.level <= self.log_level)
+            .filter(|e| {
+                self.log_device
+                    .as_ref()
+                    .is_none_or(|d| e.device.as_ref() == Some(d))
+            })
+            .collect()
+    }
+
+    /// Returns end (exclusive) of entries from `logs` shown in log view.
+    pub fn log_end(&self, logs: &[&Entry]) -> usize {
+        self.log_anchor
+            .map_or(logs.len(), |id| logs.partition_point(|e| e.id <= id))
+            .max(self.log_height.min(logs.len()))
+    }
+
+    /// Scrolls log view so that it ends at `end`, it follows the newest entry
+    /// when scrolled to the end.
+    fn scroll_logs_to(&mut self, end: usize) {
+        let logs = self.visible_logs();
+        let end = end.clamp(self.log_height.min(logs.len()), logs.len());
+        self.log_anchor = end
+            .checked_sub(1)
+            .filter(|_| end < logs.len())
+            .map(|i| logs[i].id);
+    }
+
+    pub fn scroll_logs_up(&mut self, lines: usize) {
+        let end = self.log_end(&self.visible_logs());
+        self.scroll_logs_to(end.saturating_sub(lines));
+    }
+
+    pub fn scroll_logs_down(&mut self, lines: usize) {
+        let end = self.log_end(&self.visible_logs());
+        self.scroll_logs_to(end.saturating_add(lines));
+    }
+
+    pub fn scroll_logs_page_up(&mut self) {
+        self.scroll_logs_up(self.log_height.max(1));
+    }
+
+    pub fn scroll_logs_page_down(&mut self) {
+        self.scroll_logs_down(self.log_height.max(1));
+    }
+
+    pub fn scroll_logs_top(&mut self) {
+        self.scroll_logs_to(0);
+    }
+
+    pub const fn scroll_logs_bottom(&mut self) {
+        self.log_anchor = None;
+    }
+
+    /// Scrolls logs so that closest older (or newer) entry matching search is
+    /// at the bottom of the view.
+    pub fn find_log(&mut self, older: bool) {
+        let logs = self.visible_logs();
+        let Some(bottom) = self.log_end(&logs).checked_sub(1) else {
+            return;
+        };
+        let found = if older {
+            (0..bottom)
+                .rev()
+                .find(|&i| logs[i].matches(&self.log_search))
+        } else {
+            (bottom + 1..logs.len()).find(|&i| logs[i].matches(&self.log_search))
+        };
+        if let Some(i) = found {
+            self.scroll_logs_to(i + 1);
+        }
+    }
+
+    pub const fn cycle_log_level(&mut self) {
+        self.log_level = self.log_level.cycle();
+        self.log_anchor = None;
+    }
+
+    /// Shows only entries about device under cursor, or all entries if they
+    /// are already filtered.
+    pub fn toggle_log_device(&mut self) {
+        self.log_anchor = None;
+        if self.log_device.is_some() {
+            self.log_device = None;
+        } else if let Some(dev) = self.current_device() {
+            self.log_device = Some(dev.ip.clone());
+        }
+    }
+
+    fn config_string(&self) -> Result<String> {
+        Ok(toml::to_string(&ConfigRef {
+            devices: &self.devices,
+            keys: &self.keys_config,
+            theme: &self.theme_config,
+            table: &self.table,
+            confirm_remove: self.confirm_remove,
+            backups: &self.backups,
+        })?)
+    }
+
+    /// Locks config file, if another instance holds the lock config is
+    /// opened read-only.
+    pub fn lock_config(&mut self) {
+        match file::Lock::acquire(&self.config_path) {
+            Ok(Some(v)) => {
+                self.config_lock = Some(v);
+                self.read_only = false;
+            }
+            Ok(None) => {
+                self.read_only = true;
+                log!(
+                    self,
+                    Warn,
+                    format!(
+                        "Config {} is used by another instance, opened read-only",
+                        self.config_path.to_string_lossy()
+                    )
+                );
+            }
+            Err(e) => log!(
+                self,
+                Warn,
+                format!(
+                    "failed to lock config {}, changes made by other instances may be overwritten: {e}",
+                    self.config_path.to_string_lossy()
+                )
+            ),
+        }
+    }
+
     pub fn write_config(&mut self) -> Result<()> {
-        let devices = toml::to_string(&self.devices)?;
-        fs::write(self.config_path.as_path(), devices).with_context(|| {
+        if self.read_only {
+            return Err(anyhow!(
+                "config is used by another instance, opened read-only"
+            ));
+        }
+        // changes made on disk since config was last read or written would be
+        // overwritten otherwise, file that can't be merged is left alone
+        let on_disk = fs::read_to_string(&self.config_path).ok();
+        if on_disk.is_some_and(|text| text != self.config_saved) {
+            if let Err(e) = self.reload_config() {
+                let conflict = file::with_suffix(&self.config_path, "conflict");
+                file::write(&conflict, &self.config_string()?, 0)?;
+                return Err(e.context(format!(
+                    "config file changed and can't be merged, changes made here are saved to {}",
+                    conflict.to_string_lossy()
+                )));
+            }
+        }
+        let config = self.config_string()?;
+        let backups = self.backups.unwrap_or(DEFAULT_BACKUPS);
+        file::write(&self.config_path, &config, backups).with_context(|| {
             format!(
                 "failed to write config: {}",
                 self.config_path.to_string_lossy()
             )
         })?;
+        self.config_saved = config;
+        self.config_modified = modified(&self.config_path);
         Ok(())
     }
 
-    fn current_device(&mut self) -> &mut Device {
-        &mut self.devices.bulbs[self.current_device_index]
+    /// Writes config before quitting, unless it is opened read-only.
+    pub fn quit(&mut self) -> Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+        self.write_config()
+    }
+
+    /// Merges changes of config file made by others since it was last read
+    /// or written. Devices are merged field by field, sections as a whole,
+    /// values changed here too are kept.
+    pub fn update_config(&mut self) {
+        if self.config_checked.elapsed() < CONFIG_CHECK_INTERVAL {
+            return;
+        }
+        self.config_checked = Instant::now();
+        if self.read_only {
+            if let Ok(Some(v)) = file::Lock::acquire(&self.config_path) {
+                self.config_lock = Some(v);
+                self.read_only = false;
+                log!(
+                    self,
+                    Info,
+                    "Config isn't used by another instance anymore, changes will be saved"
+                        .to_string()
+                );
+            }
+        }
+        let modified = modified(&self.config_path);
+        if modified.is_none() || modified == self.config_modified {
+            return;
+        }
+        self.config_modified = modified;
+        if let Err(e) = self.reload_config() {
+            log!(
+                self,
+                Warn,
+                format!("config file changed but can't be reloaded: {e:#}")
+            );
+        }
+    }
+
+    fn reload_config(&mut self) -> Result<()> {
+        let text = fs::read_to_string(&self.config_path)?;
+        let theirs: Config = toml::from_str(&text)?;
+        let base: Config = toml::from_str(&self.config_saved)?;
+        // bindings are checked before anything is merged
+        let keymap = Keymap::new(&theirs.keys)?;
+        let cursor = self.cursor().map(|i| self.devices.bulbs[i].ip.clone());
+
+        for entry in merge::merge_devices(&base.devices, theirs.devices, &mut self.devices) {
+            self.logs.push(entry);
+        }
+        let mut taken = Vec::new();
+        let mut conflicts = Vec::new();
+        let mut record = |section, merge| match merge {
+            Merge::Taken => taken.push(section),
+            Merge::Conflict => conflicts.push(section),
+            Merge::Unchanged => (),
+        };
+        record(
+            "keys",
+            merge::merge_field(Some(&base.keys), theirs.keys, &mut self.keys_config),
+        );
+        record(
+            "theme",
+            merge::merge_field(Some(&base.theme), theirs.theme, &mut self.theme_config),
+        );
+        record(
+            "table",
+            merge::merge_field(Some(&base.table), theirs.table, &mut self.table),
+        );
+        record(
+            "confirm_remove",
+            merge::merge_field(
+                Some(&base.confirm_remove),
+                theirs.confirm_remove,
+                &mut self.confirm_remove,
+            ),
+        );
+        record(
+            "backups",
+            merge::merge_field(Some(&base.backups), theirs.backups, &mut self.backups),
+        );
+        if taken.contains(&"keys") {
+            self.keymap = keymap;
+        }
+        if taken.contains(&"theme") {
+            self.theme = Theme::new(&self.theme_config, theme::no_color());
+        }
+        if !taken.is_empty() {
+            log!(
+                self,
+                Info,
+                format!("Reloaded from config file: {}", taken.join(", "))
+            );
+        }
+        if !conflicts.is_empty() {
+            log!(
+                self,
+                Warn,
+                format!(
+                    "Changed in config file and here: {}, keeping local",
+                    conflicts.join(", ")
+                )
+            );
+        }
+
+        self.config_saved = text;
+        let cursor = cursor.and_then(|ip| self.device_index(&ip));
+        match self.current_widget {
+            CurrentWidget::AddDevice
+                if self
+                    .editing
+                    .as_ref()
+                    .is_some_and(|ip| self.device_index(ip).is_none()) =>
+            {
+                log!(
+                    self,
+                    Warn,
+                    "Edited device was removed in config file".to_string()
+                );
+                self.close_popup();
+            }
+            CurrentWidget::ConfirmRemove if cursor.is_none() => {
+                log!(
+                    self,
+                    Warn,
+                    "Device to remove was removed in config file".to_string()
+                );
+                self.close_popup();
+            }
+            _ => (),
+        }
+        // out of range index makes filter put cursor on first visible device
+        self.current_device_index = cursor.unwrap_or(self.devices.bulbs.len());
+        self.filter_devices();
+        Ok(())
+    }
+
+    /// Returns index of device under cursor, `None` if there is no visible
+    /// device for cursor to be on.
+    pub fn cursor(&self) -> Option<usize> {
+        self.devices
+            .bulbs
+            .get(self.current_device_index)
+            .filter(|d| !d.hidden)
+            .map(|_| self.current_device_index)
+    }
+
+    fn current_device(&mut self) -> Option<&mut Device> {
+        self.cursor().map(|i| &mut self.devices.bulbs[i])
+    }
+
+    fn device_index(&self, ip: &str) -> Option<usize> {
+        self.devices.bulbs.iter().position(|d| d.ip == ip)
+    }
+
+    /// Returns indices of visible devices in order they are listed.
+    pub fn device_order(&self) -> Vec<usize> {
+        let bulbs = &self.devices.bulbs;
+        let mut order: Vec<usize> = (0..bulbs.len()).filter(|&i| !bulbs[i].hidden).collect();
+        order.sort_by(|&a, &b| self.table.compare(&bulbs[a], &bulbs[b]));
+        order
+    }
+
+    /// Moves cursor by `step` rows of device list.
+    fn move_cursor(&mut self, step: isize) {
+        let order = self.device_order();
+        let pos = order
+            .iter()
+            .position(|&i| i == self.current_device_index)
+            .map_or(0, |p| p.saturating_add_signed(step));
+        if let Some(&i) = order.get(pos.min(order.len().saturating_sub(1))) {
+            self.current_device_index = i;
+        }
     }
 
     pub fn prev_device(&mut self) {
-        self.current_device_index = self.current_device_index.saturating_sub(1);
+        self.move_cursor(-1);
     }
 
     pub fn next_device(&mut self) {
-        if self.current_device_index < self.devices.bulbs.len().saturating_sub(1) {
-            self.current_device_index = self.current_device_index.saturating_add(1);
+        self.move_cursor(1);
+    }
+
+    /// Changes selection of all devices at once.
+    pub fn select_devices(&mut self, selection: Selection) {
+        selection.apply(&mut self.devices);
+        let count = self.devices.bulbs.iter().filter(|d| d.selected).count();
+        log!(
+            self,
+            Info,
+            format!("{count} of {} devices selected", self.devices.bulbs.len())
+        );
+    }
+
+    /// Moves current device by `step` rows in config order, skipping hidden
+    /// devices. Sorting by column is turned off, so that move is visible.
+    pub fn move_device(&mut self, step: isize) {
+        if let Some(column) = self.table.sort.take() {
+            log!(
+                self,
+                Info,
+                format!(
+                    "Not sorting by {} anymore, devices are listed in config order",
+                    column.title().to_lowercase()
+                )
+            );
+        }
+        let order = self.device_order();
+        let Some(pos) = order.iter().position(|&i| i == self.current_device_index) else {
+            return;
+        };
+        // hidden devices in between keep their places, current device is put
+        // just past its visible neighbour
+        if let Some(&other) = pos.checked_add_signed(step).and_then(|p| order.get(p)) {
+            let dev = self.devices.bulbs.remove(self.current_device_index);
+            self.devices.bulbs.insert(other, dev);
+            self.current_device_index = other;
         }
     }
 
+    /// Hides devices that don't match filter and moves cursor off hidden
+    /// device.
+    pub fn filter_devices(&mut self) {
+        for dev in &mut self.devices.bulbs {
+            dev.hidden = !dev.matches(&self.device_filter);
+        }
+        if self
+            .devices
+            .bulbs
+            .get(self.current_device_index)
+            .is_none_or(|d| d.hidden)
+        {
+            self.current_device_index = self.device_order().first().copied().unwrap_or(0);
+        }
+    }
+
+    pub fn clear_device_filter(&mut self) {
+        self.device_filter.clear();
+        self.filter_devices();
+    }
+
     pub fn select_device(&mut self) {
-        if !self.devices.bulbs.is_empty() {
-            self.current_device().selected = !self.current_device().selected;
+        if let Some(dev) = self.current_device() {
+            dev.selected = !dev.selected;
         }
     }
 
+    /// Removes current device, or asks first if removal must be confirmed.
     pub fn remove_device(&mut self) {
-        if !self.devices.bulbs.is_empty() {
-            self.devices.bulbs.remove(self.current_device_index);
-            self.prev_device();
+        if self.cursor().is_none() {
+            return;
+        }
+        if self.confirm_remove {
+            self.current_widget = CurrentWidget::ConfirmRemove;
+        } else {
+            self.remove_current();
         }
     }
 
-    pub fn add_device(&mut self) {
-        if self.devices.bulbs.iter().any(|x| x.ip == self.ip_input) {
-            log!(self, format!("Device \"{}\" already added", self.ip_input));
+    /// Removes current device after confirmation.
+    pub fn confirm_removal(&mut self) {
+        self.current_widget = CurrentWidget::Devices;
+        self.remove_current();
+    }
+
+    /// Moves current device to trash.
+    fn remove_current(&mut self) {
+        let Some(removed) = self.cursor() else {
+            return;
+        };
+        // cursor goes to previous row, or next one if first was removed
+        let order = self.device_order();
+        let pos = order.iter().position(|&i| i == removed).unwrap_or(0);
+        let next = pos
+            .checked_sub(1)
+            .and_then(|p| order.get(p))
+            .or_else(|| order.get(pos + 1));
+        let dev = self.devices.bulbs.remove(removed);
+        let following = self.devices.bulbs.get(removed).map(|d| d.ip.clone());
+        log!(
+            self,
+            Info,
+            dev.ip.clone(),
+            format!("Device removed, {} in trash", self.trash.len() + 1)
+        );
+        self.trash.push((following, dev));
+        self.current_device_index = next.map_or(0, |&i| if i > removed { i - 1 } else { i });
+        self.filter_devices();
+    }
+
+    /// Brings back device removed last, to its former place in config.
+    pub fn restore_device(&mut self) {
+        let Some((following, dev)) = self.trash.pop() else {
+            log!(self, Info, "Trash is empty".to_string());
+            return;
+        };
+        if self.devices.bulbs.iter().any(|d| d.ip == dev.ip) {
+            log!(self, Warn, dev.ip, "Device already added".to_string());
             return;
         }
-        if !self.ip_input.is_empty() {
-            match self
-                .devices
-                .add(self.ip_input.clone(), self.name_input.clone())
-            {
-                Ok(v) => log!(self, v),
-                Err(e) => {
-                    log!(self, e.to_string());
-                    return;
-                }
+        let bulbs = &self.devices.bulbs;
+        let index = following
+            .and_then(|ip| bulbs.iter().position(|d| d.ip == ip))
+            .unwrap_or(bulbs.len());
+        log!(self, Info, dev.ip.clone(), "Device restored".to_string());
+        self.devices.bulbs.insert(index, dev);
+        self.current_device_index = index;
+        self.filter_devices();
+    }
+
+    /// Opens add device popup filled with current device.
+    pub fn open_edit(&mut self) {
+        if let Some(i) = self.cursor() {
+            let dev = &self.devices.bulbs[i];
+            self.ip_input.set(dev.ip.clone());
+            self.name_input.set(dev.name.clone());
+            self.editing = Some(dev.ip.clone());
+            self.current_widget = CurrentWidget::AddDevice;
+            self.currently_adding = Some(CurrentlyAdding::Name);
+        }
+    }
+
+    /// Applies add device popup used for editing, status is fetched again if
+    /// address changed.
+    pub fn edit_device(&mut self) {
+        let Some(i) = self.editing.as_ref().and_then(|ip| self.device_index(ip)) else {
+            return;
+        };
+        let ip = self.ip_input.value().trim().to_string();
+        if let Err(e) = check_address(&ip) {
+            log!(self, Warn, ip, e.to_string());
+            return;
+        }
+        let taken = self
+            .devices
+            .bulbs
+            .iter()
+            .enumerate()
+            .any(|(j, d)| j != i && d.ip == ip);
+        if taken {
+            log!(self, Warn, ip, "Device already added".to_string());
+            return;
+        }
+
+        let dev = &mut self.devices.bulbs[i];
+        dev.name = self.name_input.value().trim().to_string();
+        if dev.ip == ip {
+            log!(self, Info, ip, "Device changed".to_string());
+        } else {
+            let old = dev.ip.clone();
+            match self.devices.readdress(i, ip.clone()) {
+                Ok(v) => log!(self, Info, ip, format!("Device moved from {old}: {v}")),
+                Err(e) => log!(self, Error, ip, format!("Device moved from {old}: {e}")),
+            }
+        }
+        self.filter_devices();
+        self.close_popup();
+    }
+
+    pub fn add_device(&mut self) {
+        if !self.ip_input.value().is_empty() {
+            let ip = self.ip_input.value().trim().to_string();
+            let name = self.name_input.value().trim().to_string();
+            if !self.insert_device(ip, name) {
+                return;
             }
             self.ip_input.clear();
             self.name_input.clear();
@@ -167,14 +1200,54 @@ impl App {
         self.current_widget = CurrentWidget::Devices;
     }
 
+    /// Adds device to list, returns false if it can't be added.
+    fn insert_device(&mut self, ip: String, name: String) -> bool {
+        if let Err(e) = check_address(&ip) {
+            log!(self, Warn, ip, e.to_string());
+            return false;
+        }
+        if self.devices.bulbs.iter().any(|x| x.ip == ip) {
+            log!(self, Warn, ip, "Device already added".to_string());
+            return false;
+        }
+        match self.devices.add(ip.clone(), name) {
+            Ok(v) => {
+                log!(self, Info, ip, v);
+                self.filter_devices();
+                true
+            }
+            Err(e) => {
+                log!(self, Error, ip, e.to_string());
+                false
+            }
+        }
+    }
+
     pub fn refresh_devices(&mut self) {
         if self.devices.bulbs.is_empty() {
             return;
         }
-        match self.devices.get_status() {
-            Ok(None) => (),
-            Ok(Some(v)) => log!(self, v),
-            Err(e) => log!(self, e.to_string()),
+        let before: Vec<_> = self
+            .devices
+            .bulbs
+            .iter()
+            .map(|d| (d.bulb.clone(), d.stats.last_response.is_some()))
+            .collect();
+        for (ip, res) in self.devices.refresh() {
+            let Some(i) = self.devices.bulbs.iter().position(|d| d.ip == ip) else {
+                continue;
+            };
+            let (old, seen) = &before[i];
+            // only first response and changes are logged, refreshes are frequent
+            match res {
+                Ok(v) if !seen => log!(self, Debug, ip, v),
+                Ok(_) => {
+                    if let Some(changes) = old.changes(&self.devices.bulbs[i].bulb) {
+                        log!(self, Debug, ip, format!("State changed: {changes}"));
+                    }
+                }
+                Err(e) => log!(self, Error, ip, e.to_string()),
+            }
         }
     }
 
@@ -182,65 +1255,377 @@ impl App {
         match api::discover_bulbs(200) {
             Ok(v) => {
                 if v.is_empty() {
-                    log!(self, "No devices detected".to_string());
+                    log!(self, Info, "No devices detected".to_string());
                 }
                 for ip in v {
                     if !self.devices.bulbs.iter().any(|x| x.ip == ip) {
-                        match self.devices.add(ip, String::new()) {
-                            Ok(v) => log!(self, v),
+                        match self.devices.add(ip.clone(), String::new()) {
+                            Ok(v) => log!(self, Info, ip, v),
                             Err(e) => {
-                                log!(self, e.to_string());
+                                log!(self, Error, ip, e.to_string());
                                 return;
                             }
                         }
                     }
                 }
             }
-            Err(e) => log!(self, e.to_string()),
+            Err(e) => log!(self, Error, e.to_string()),
+        }
+        self.filter_devices();
+    }
+
+    pub fn open_command(&mut self) {
+        self.command_return = self.current_widget;
+        self.current_widget = CurrentWidget::Command;
+        self.command_input.clear();
+        self.command_hint.clear();
+        self.command_pos = None;
+    }
+
+    pub fn close_command(&mut self) {
+        self.current_widget = self.command_return;
+        self.command_hint.clear();
+    }
+
+    /// Completes command or device name in command line.
+    pub fn complete_command(&mut self) {
+        let names: Vec<&str> = self
+            .devices
+            .bulbs
+            .iter()
+            .flat_map(|d| [d.name.as_str(), d.ip.as_str()])
+            .filter(|n| !n.is_empty() && !n.contains(','))
+            .collect();
+        (self.command_input, self.command_hint) = command::complete(&self.command_input, &names);
+    }
+
+    /// Replaces command line with older entry of history, or newer one if
+    /// `older` is false.
+    pub fn browse_commands(&mut self, older: bool) {
+        let len = self.commands.len();
+        self.command_pos = match (self.command_pos, older) {
+            (None, true) if len > 0 => Some(len - 1),
+            (Some(i), true) => Some(i.saturating_sub(1)),
+            (Some(i), false) if i + 1 < len => Some(i + 1),
+            (Some(_), false) => {
+                self.command_input.clear();
+                None
+            }
+            (pos, _) => pos,
+        };
+        if let Some(line) = self.command_pos.and_then(|i| self.commands.get(i)) {
+            self.command_input = line.to_string();
+        }
+        self.command_hint.clear();
+    }
+
+    /// Runs command entered in command line and adds it to history.
+    pub fn run_command(&mut self) {
+        self.close_command();
+        let line = std::mem::take(&mut self.command_input);
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        if let Err(e) = self.commands.push(line) {
+            log!(self, Warn, format!("failed to save history: {e}"));
+        }
+        let res = line.parse().and_then(|cmd| self.execute(cmd));
+        if let Err(e) = res {
+            log!(self, Error, format!("{line}: {e}"));
+        }
+    }
+
+    fn execute(&mut self, cmd: Command) -> Result<()> {
+        match cmd {
+            Command::Color(color, targets) => {
+                self.change_devices(&targets, |bulb| bulb.color.clone_from(&color))?;
+            }
+            Command::Brightness(brightness, targets) => {
+                self.change_devices(&targets, |bulb| bulb.brightness = brightness)?;
+            }
+            Command::Power(power, targets) => {
+                self.change_devices(&targets, |bulb| {
+                    bulb.enabled = match power {
+                        Power::On => 1,
+                        Power::Off => 0,
+                        Power::Toggle => u8::from(bulb.enabled != 1),
+                    };
+                })?;
+            }
+            Command::Select(selection) => self.select_devices(selection),
+            Command::Discover => self.discover(),
+            Command::Add(ip, name) => _ = self.insert_device(ip, name),
+            Command::Refresh => self.refresh_devices(),
+            Command::Write => {
+                self.write_config()?;
+                log!(
+                    self,
+                    Info,
+                    format!("Config written to {}", self.config_path.to_string_lossy())
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Changes state of devices named in `targets`, or selected ones if there
+    /// are none. Names are matched case insensitively, addresses exactly.
+    fn change_devices(&mut self, targets: &[String], change: impl Fn(&mut Bulb)) -> Result<()> {
+        let before: Snapshot = if targets.is_empty() {
+            self.devices.snapshot()
+        } else {
+            targets
+                .iter()
+                .map(|t| {
+                    self.devices
+                        .bulbs
+                        .iter()
+                        .find(|d| d.ip == *t || d.name.eq_ignore_ascii_case(t))
+                        .map(|d| (d.ip.clone(), d.bulb.clone()))
+                        .ok_or_else(|| anyhow!("no such device: {t}"))
+                })
+                .collect::<Result<_>>()?
+        };
+        let after: Snapshot = before
+            .iter()
+            .cloned()
+            .map(|(ip, mut bulb)| {
+                change(&mut bulb);
+                (ip, bulb)
+            })
+            .collect();
+        let res = self.devices.restore(&after);
+        self.record_change(before);
+        res
+    }
+
+    /// Returns text field focused in popup.
+    pub const fn focused_input(&mut self) -> Option<&mut Input> {
+        match (
+            &self.current_widget,
+            &self.currently_adding,
+            &self.currently_setting,
+        ) {
+            (CurrentWidget::AddDevice, Some(CurrentlyAdding::IP), _) => Some(&mut self.ip_input),
+            (CurrentWidget::AddDevice, Some(CurrentlyAdding::Name), _) => {
+                Some(&mut self.name_input)
+            }
+            (CurrentWidget::DeviceSettings, _, Some(CurrentlySetting::Color)) => {
+                Some(&mut self.color_input)
+            }
+            (CurrentWidget::DeviceSettings, _, Some(CurrentlySetting::Brightness)) => {
+                Some(&mut self.brightness_input)
+            }
+            _ => None,
+        }
+    }
+
+    /// Edits focused text field, color picker and live preview follow edits
+    /// in device settings.
+    pub fn edit_input(&mut self, key: &KeyEvent) {
+        if self.focused_input().is_some_and(|i| i.handle_key(key)) {
+            self.input_changed();
+        }
+    }
+
+    /// Inserts pasted text into focused text field or prompt.
+    pub fn paste(&mut self, text: &str) {
+        match self.current_widget {
+            CurrentWidget::DeviceSearch => {
+                self.device_filter.push_str(&input::single_line(text));
+                self.filter_devices();
+            }
+            CurrentWidget::LogSearch => self.log_search.push_str(&input::single_line(text)),
+            CurrentWidget::Command => self.command_input.push_str(&input::single_line(text)),
+            CurrentWidget::AddDevice | CurrentWidget::DeviceSettings => {
+                if let Some(input) = self.focused_input() {
+                    input.paste(text);
+                    self.input_changed();
+                }
+            }
+            CurrentWidget::Devices | CurrentWidget::Logs | CurrentWidget::ConfirmRemove => (),
+        }
+    }
+
+    fn input_changed(&mut self) {
+        match self.currently_setting {
+            Some(CurrentlySetting::Color) => self.sync_picker(),
+            Some(CurrentlySetting::Brightness) => self.schedule_preview(),
+            _ => (),
+        }
+    }
+
+    /// Returns current state of devices from `snapshot`.
+    fn current_state(&self, snapshot: &Snapshot) -> Snapshot {
+        snapshot
+            .iter()
+            .filter_map(|(ip, _)| self.devices.bulbs.iter().find(|d| d.ip == *ip))
+            .map(|d| (d.ip.clone(), d.bulb.clone()))
+            .collect()
+    }
+
+    /// Records state of devices that changed since `before` was taken.
+    fn record_change(&mut self, before: Snapshot) {
+        let changed = before
+            .into_iter()
+            .filter(|(ip, bulb)| {
+                self.devices
+                    .bulbs
+                    .iter()
+                    .any(|d| d.ip == *ip && d.bulb != *bulb)
+            })
+            .collect();
+        self.history.record(changed);
+    }
+
+    pub fn undo(&mut self) {
+        let Some(state) = self.history.pop_undo() else {
+            log!(self, Info, "Nothing to undo".to_string());
+            return;
+        };
+        let current = self.current_state(&state);
+        match self.devices.restore(&state) {
+            Ok(()) => {
+                log!(
+                    self,
+                    Info,
+                    format!("Undone change of {} devices", state.len())
+                );
+                self.history.push_redo(current);
+            }
+            Err(e) => {
+                // kept, so that undo can be retried once devices respond
+                log!(self, Error, format!("failed to undo: {e}"));
+                self.history.push_undo(state);
+            }
+        }
+    }
+
+    pub fn redo(&mut self) {
+        let Some(state) = self.history.pop_redo() else {
+            log!(self, Info, "Nothing to redo".to_string());
+            return;
+        };
+        let current = self.current_state(&state);
+        match self.devices.restore(&state) {
+            Ok(()) => {
+                log!(
+                    self,
+                    Info,
+                    format!("Redone change of {} devices", state.len())
+                );
+                self.history.push_undo(current);
+            }
+            Err(e) => {
+                log!(self, Error, format!("failed to redo: {e}"));
+                self.history.push_redo(state);
+            }
         }
     }
 
     pub fn toggle_selected(&mut self) {
+        let before = self.devices.snapshot();
         match self.devices.toggle() {
             Ok(()) => (),
-            Err(e) => log!(self, e.to_string()),
+            Err(e) => log!(self, Error, e.to_string()),
         }
+        self.record_change(before);
     }
 
     pub fn toggle_current(&mut self) {
-        if !self.devices.bulbs.is_empty() {
-            if let Err(e) = self.devices.toggle_one(self.current_device_index) {
-                log!(self, e.to_string());
-            }
+        let Some(i) = self.cursor() else {
+            return;
+        };
+        let dev = &self.devices.bulbs[i];
+        let before = vec![(dev.ip.clone(), dev.bulb.clone())];
+        if let Err(e) = self.devices.toggle_one(i) {
+            let ip = self.devices.bulbs[i].ip.clone();
+            log!(self, Error, ip, e.to_string());
         }
+        self.record_change(before);
     }
 
-    pub fn set_color_and_brightness(&mut self) {
-        if !self.color_input.is_empty() && self.color_input.len() == 7 {
-            if let Err(e) = self.devices.set_color(&self.color_input) {
-                log!(self, e.to_string());
-                return;
-            }
-        } else {
-            log!(self, format!("failed to set color: wrong input lenght"));
+    /// Changes pending state of selected devices, it is sent once nudges
+    /// pause.
+    fn nudge(&mut self, change: impl Fn(&mut Bulb)) {
+        for dev in self.devices.bulbs.iter().filter(|d| d.is_active()) {
+            let i = self
+                .nudged
+                .iter()
+                .position(|(ip, _)| *ip == dev.ip)
+                .unwrap_or_else(|| {
+                    self.nudged.push((dev.ip.clone(), dev.bulb.clone()));
+                    self.nudged.len() - 1
+                });
+            change(&mut self.nudged[i].1);
+        }
+        self.nudge_due = Some(Instant::now() + NUDGE_DELAY);
+    }
+
+    /// Changes brightness of selected devices by `steps` steps.
+    pub fn nudge_brightness(&mut self, steps: f32) {
+        self.nudge(|bulb| {
+            let brightness = steps.mul_add(BRIGHTNESS_STEP, bulb.brightness);
+            // keep values round despite float errors
+            bulb.brightness = (brightness.clamp(0.0, 1.0) * 100.0).round() / 100.0;
+        });
+    }
+
+    pub fn set_brightness_level(&mut self, brightness: f32) {
+        self.nudge(|bulb| bulb.brightness = brightness);
+    }
+
+    /// Rotates hue of selected devices by `steps` steps.
+    pub fn nudge_hue(&mut self, steps: f32) {
+        self.nudge(|bulb| {
+            let (r, g, b) = bulb.rgb();
+            let hsv = Hsv::from_rgb(r, g, b);
+            bulb.color = Hsv::new(steps.mul_add(NUDGE_HUE_STEP, hsv.h), hsv.s, hsv.v).to_hex();
+        });
+    }
+
+    /// Sends nudged state to devices if nudges paused.
+    pub fn update_nudge(&mut self) {
+        if self.nudge_due.is_none_or(|due| due > Instant::now()) {
             return;
         }
+        self.nudge_due = None;
 
-        let brightness = self.brightness_input.parse::<f32>();
-        match brightness {
-            Ok(v) => {
-                // compare floats with error margin, ty clippy
-                let error_margin = f32::EPSILON;
-                if (v - self.current_device().bulb.brightness).abs() > error_margin {
-                    if let Err(e) = self.devices.set_brightness(v) {
-                        log!(self, e.to_string());
-                    }
-                }
-            }
+        let nudged = std::mem::take(&mut self.nudged);
+        let before = self.current_state(&nudged);
+        if let Err(e) = self.devices.restore(&nudged) {
+            log!(self, Error, format!("failed to change devices: {e}"));
+        }
+        self.record_change(before);
+    }
+
+    pub fn set_color_and_brightness(&mut self) {
+        // live preview may have changed devices already
+        let before = self.settings_before.clone();
+        self.preview_due = None;
+        self.send_settings();
+        self.record_change(before);
+    }
+
+    fn send_settings(&mut self) {
+        let color = self.color_input.value();
+        if let Err(e) = check_color(color) {
+            log!(self, Error, format!("failed to set color: {e}"));
+            return;
+        }
+        let brightness = match command::parse_brightness(self.brightness_input.value()) {
+            Ok(v) => v,
             Err(e) => {
-                log!(self, format!("failed to set brightness: {e}"));
+                log!(self, Error, format!("failed to set brightness: {e}"));
                 return;
             }
+        };
+        // unchanged values aren't sent
+        let states = self.settings_state(Some(color), Some(brightness));
+        if let Err(e) = self.devices.restore(&states) {
+            log!(self, Error, e.to_string());
+            return;
         }
 
         self.currently_setting = None;
@@ -248,15 +1633,103 @@ impl App {
     }
 }
 
-pub fn load_devices(path: PathBuf) -> Result<Devices> {
+fn check_color(color: &str) -> Result<()> {
+    Hsv::from_hex(color)
+        .map(drop)
+        .ok_or_else(|| anyhow!("expected #RRGGBB"))
+}
+
+/// Returns modification time of file, if it can be read.
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Checks that address is a host with optional port. IPv6 address can be
+/// bare, or in brackets when followed by port.
+fn check_address(ip: &str) -> Result<()> {
+    if ip.is_empty() {
+        return Err(anyhow!("address can't be empty"));
+    }
+    if ip.contains(|c: char| c.is_whitespace() || c == '/') {
+        return Err(anyhow!("address can't contain spaces or /"));
+    }
+    if ip.parse::<Ipv6Addr>().is_ok() {
+        return Ok(());
+    }
+    let port = if let Some(rest) = ip.strip_prefix('[') {
+        let (addr, rest) = rest
+            .split_once(']')
+            .ok_or_else(|| anyhow!("missing ] in address"))?;
+        addr.parse::<Ipv6Addr>().context("invalid IPv6 address")?;
+        if rest.is_empty() {
+            None
+        } else {
+            let port = rest.strip_prefix(':');
+            Some(port.ok_or_else(|| anyhow!("expected :port after ]"))?)
+        }
+    } else {
+        ip.rsplit_once(':').map(|(_, port)| port)
+    };
+    if let Some(port) = port {
+        port.parse::<u16>().context("invalid port in address")?;
+    }
+    Ok(())
+}
+
+pub fn load_config(path: PathBuf) -> Result<Config> {
     let cfg = match fs::read_to_string(path) {
         Ok(v) => v,
         Err(e) => {
             if e.kind() == io::ErrorKind::NotFound {
-                return Ok(Devices::new());
+                return Ok(Config {
+                    devices: Devices::new(),
+                    keys: KeysConfig::new(),
+                    theme: ThemeConfig::default(),
+                    table: TableConfig::default(),
+                    confirm_remove: true,
+                    backups: None,
+                });
             }
             return Err(e.into());
         }
     };
     toml::from_str(cfg.as_str()).map_err(std::convert::Into::into)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_address_accepts_hosts_and_ports() {
+        for ip in [
+            "192.168.1.10",
+            "192.168.1.10:8080",
+            "bulb.local",
+            "bulb.local:80",
+            "::1",
+            "fe80::a",
+            "[fe80::a]",
+            "[::1]:8080",
+        ] {
+            assert!(check_address(ip).is_ok(), "{ip}");
+        }
+    }
+
+    #[test]
+    fn check_address_rejects_malformed_addresses() {
+        for ip in [
+            "",
+            "bulb local",
+            "bulb/led",
+            "bulb:port",
+            "bulb:99999",
+            "[::1",
+            "[bulb]:80",
+            "[::1]80",
+            "[::1]:x",
+        ] {
+            assert!(check_address(ip).is_err(), "{ip}");
+        }
+    }
+}
@@ -1,8 +1,12 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use serde::{Deserialize, Serialize};
 use std::{ffi::OsString, path::PathBuf};
 
-use crate::api::{self, Device, Devices};
+use crate::{
+    api::{self, Device, Devices, Selection},
+    ctl, hass,
+};
 
 pub fn parse() -> Args {
     Args::parse()
@@ -15,6 +19,18 @@ pub struct Args {
     #[arg(long,  default_value=xdg_cfg_path())]
     pub config: PathBuf,
 
+    /// Path to control socket of running TUI
+    #[arg(long, default_value=xdg_socket_path())]
+    pub socket: PathBuf,
+
+    /// Path to log file of TUI
+    #[arg(long, default_value=xdg_log_path())]
+    pub log_file: PathBuf,
+
+    /// Path to history file of TUI command line
+    #[arg(long, default_value=xdg_history_path())]
+    pub history_file: PathBuf,
+
     #[command(subcommand)]
     pub cmd: Option<Subcmd>,
 }
@@ -23,9 +39,18 @@ pub struct Args {
 pub enum Subcmd {
     /// Control bulbs non interactively
     Cli(Cli),
+    /// Serve Prometheus metrics of configured devices
+    Exporter(Exporter),
 }
 
 #[derive(clap::Args, Debug)]
+pub struct Exporter {
+    /// Address to listen on
+    #[arg(short, value_name = "ADDR", default_value = "127.0.0.1:9977")]
+    pub listen: String,
+}
+
+#[derive(clap::Args, Debug, Deserialize, Serialize)]
 pub struct Cli {
     /// Device address (can be specified mulitiple times)
     #[arg(short, value_name = "ADDR")]
@@ -47,11 +72,23 @@ pub struct Cli {
     #[arg(short)]
     status: bool,
 
+    /// Print Home Assistant MQTT discovery and state messages
+    #[arg(short = 'H')]
+    hass: bool,
+
+    /// Act on devices picked by selector instead of selected ones
+    #[arg(short = 'S', value_name = "SELECTOR")]
+    select: Option<Selection>,
+
+    /// Act only on devices whose name, address or group fuzzy matches term
+    #[arg(short = 'f', value_name = "TERM")]
+    filter: Option<String>,
+
     /// Set LED power
     power: Option<PowerState>,
 }
 
-#[derive(clap::ValueEnum, Clone, Debug)]
+#[derive(clap::ValueEnum, Clone, Debug, Deserialize, Serialize)]
 pub enum PowerState {
     On,
     Off,
@@ -59,9 +96,13 @@ pub enum PowerState {
 }
 
 impl Cli {
-    pub fn run(&self, devices: &mut Devices) -> Result<Option<String>> {
-        let mut status: Option<String> = None;
+    /// Returns true if command operates on configured devices and therefore
+    /// should be handled by running TUI instance, if there is one.
+    pub const fn is_forwardable(&self) -> bool {
+        self.addrs.is_empty()
+    }
 
+    pub fn run(&self, devices: &mut Devices) -> Result<Option<String>> {
         if !self.addrs.is_empty() {
             *devices = Devices::new();
             for a in &self.addrs {
@@ -72,7 +113,9 @@ impl Cli {
         if self.discover {
             let discovered_devices = api::discover_bulbs(200)?;
             for a in discovered_devices {
-                devices.bulbs.push(Device::new(a, String::new()));
+                if !devices.bulbs.iter().any(|x| x.ip == a) {
+                    devices.bulbs.push(Device::new(a, String::new()));
+                }
             }
         }
 
@@ -80,6 +123,32 @@ impl Cli {
             return Err(CliError::NoDevicesError.into());
         }
 
+        // selector and filter only apply to this command
+        let selected: Vec<bool> = devices.bulbs.iter().map(|d| d.selected).collect();
+        if let Some(term) = &self.filter {
+            for dev in &mut devices.bulbs {
+                dev.hidden = !dev.matches(term);
+            }
+        }
+        if let Some(selection) = self.select {
+            if matches!(selection, Selection::On | Selection::Off) {
+                // power state is needed to pick devices, unreachable ones
+                // fail later
+                Selection::All.apply(devices);
+                _ = devices.refresh();
+            }
+            selection.apply(devices);
+        }
+        let res = self.act(devices);
+        for (dev, selected) in devices.bulbs.iter_mut().zip(selected) {
+            dev.selected = selected;
+            dev.hidden = false;
+        }
+        res
+    }
+
+    fn act(&self, devices: &mut Devices) -> Result<Option<String>> {
+        let mut status: Option<String> = None;
         let mut sth_was_done = false;
         if let Some(brght) = self.brightness {
             sth_was_done = true;
@@ -104,6 +173,27 @@ impl Cli {
             sth_was_done = true;
             status = devices.get_status()?;
         }
+        if self.hass {
+            // unreachable devices are left out rather than failing the rest
+            let offline: Vec<String> = if sth_was_done {
+                Vec::new()
+            } else {
+                devices
+                    .refresh()
+                    .into_iter()
+                    .filter(|(_, res)| res.is_err())
+                    .map(|(ip, _)| ip)
+                    .collect()
+            };
+            sth_was_done = true;
+            let msgs = hass::messages(
+                devices
+                    .bulbs
+                    .iter()
+                    .filter(|d| d.is_active() && !offline.contains(&d.ip)),
+            );
+            status = Some(status.map_or_else(|| msgs.clone(), |s| s + "\n" + &msgs));
+        }
 
         if sth_was_done {
             Ok(status)
@@ -143,3 +233,29 @@ fn xdg_cfg_path() -> OsString {
         .unwrap_or_else(|_| "config.toml".into())
         .into_os_string()
 }
+
+fn xdg_socket_path() -> OsString {
+    #[allow(clippy::expect_used)]
+    let xdg_dirs = xdg::BaseDirectories::with_prefix("bulbs").expect("failed to get XDG dirs");
+    xdg_dirs
+        .place_runtime_file("tui.sock")
+        .unwrap_or_else(|_| {
+            // created by TUI with mode 0700, see ctl::Server::bind
+            std::env::temp_dir()
+                .join(format!("bulbs-{}", ctl::uid()))
+                .join("tui.sock")
+        })
+        .into_os_string()
+}
+
+fn xdg_history_path() -> OsString {
+    #[allow(clippy::expect_used)]
+    let xdg_dirs = xdg::BaseDirectories::with_prefix("bulbs").expect("failed to get XDG dirs");
+    xdg_dirs.get_state_file("history").into_os_string()
+}
+
+fn xdg_log_path() -> OsString {
+    #[allow(clippy::expect_used)]
+    let xdg_dirs = xdg::BaseDirectories::with_prefix("bulbs").expect("failed to get XDG dirs");
+    xdg_dirs.get_state_file("tui.log").into_os_string()
+}
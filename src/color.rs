@@ -0,0 +1,108 @@
+/// Color in HSV space, hue is in degrees, saturation and value in range 0..1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hsv {
+    pub h: f32,
+    pub s: f32,
+    pub v: f32,
+}
+
+impl Default for Hsv {
+    fn default() -> Self {
+        Self {
+            h: 0.0,
+            s: 0.0,
+            v: 1.0,
+        }
+    }
+}
+
+impl Hsv {
+    pub fn new(h: f32, s: f32, v: f32) -> Self {
+        Self {
+            h: h.rem_euclid(360.0),
+            s: s.clamp(0.0, 1.0),
+            v: v.clamp(0.0, 1.0),
+        }
+    }
+
+    #[allow(clippy::many_single_char_names)]
+    pub fn from_rgb(r: u8, g: u8, b: u8) -> Self {
+        let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+        let delta = f32::from(max - min);
+        let (fr, fg, fb) = (f32::from(r), f32::from(g), f32::from(b));
+        let h = if max == min {
+            0.0
+        } else if max == r {
+            60.0 * ((fg - fb) / delta)
+        } else if max == g {
+            60.0 * ((fb - fr) / delta + 2.0)
+        } else {
+            60.0 * ((fr - fg) / delta + 4.0)
+        };
+        let s = if max == 0 {
+            0.0
+        } else {
+            delta / f32::from(max)
+        };
+        Self::new(h, s, f32::from(max) / 255.0)
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::many_single_char_names
+    )]
+    pub fn to_rgb(self) -> (u8, u8, u8) {
+        let c = self.v * self.s;
+        let x = c * (1.0 - ((self.h / 60.0) % 2.0 - 1.0).abs());
+        let m = self.v - c;
+        let (r, g, b) = match self.h {
+            h if h < 60.0 => (c, x, 0.0),
+            h if h < 120.0 => (x, c, 0.0),
+            h if h < 180.0 => (0.0, c, x),
+            h if h < 240.0 => (0.0, x, c),
+            h if h < 300.0 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        let channel = |v: f32| ((v + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+        (channel(r), channel(g), channel(b))
+    }
+
+    /// Parses color in `#RRGGBB` format.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#')?;
+        // from_str_radix alone would let signs like `+f` through
+        if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        Some(Self::from_rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+
+    pub fn to_hex(self) -> String {
+        let (r, g, b) = self.to_rgb();
+        format!("#{r:02X}{g:02X}{b:02X}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_round_trips() {
+        for hex in ["#000000", "#FFFFFF", "#FF0000", "#E65C17", "#123456"] {
+            assert_eq!(Hsv::from_hex(hex).unwrap().to_hex(), hex);
+        }
+        assert_eq!(Hsv::from_hex("#e65c17").unwrap().to_hex(), "#E65C17");
+    }
+
+    #[test]
+    fn rejects_invalid_hex() {
+        for hex in [
+            "", "#", "ff0000", "#ff000", "#ff00000", "#gg0000", "#+f+f+f", "#-f0000",
+        ] {
+            assert!(Hsv::from_hex(hex).is_none(), "{hex}");
+        }
+    }
+}
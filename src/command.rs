@@ -0,0 +1,339 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, Context, Result};
+
+use clap::ValueEnum;
+
+use crate::{api::Selection, color::Hsv};
+
+/// Number of command lines kept in history.
+const HISTORY_SIZE: usize = 500;
+
+/// Names of commands, for completion.
+const NAMES: &[&str] = &[
+    "add",
+    "brightness",
+    "color",
+    "discover",
+    "off",
+    "on",
+    "refresh",
+    "select",
+    "toggle",
+    "write",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Power {
+    On,
+    Off,
+    Toggle,
+}
+
+/// Command typed in command line. Targets are names or addresses of devices,
+/// empty for selected ones.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Color(String, Vec<String>),
+    Brightness(f32, Vec<String>),
+    Power(Power, Vec<String>),
+    Select(Selection),
+    Discover,
+    Add(String, String),
+    Refresh,
+    Write,
+}
+
+impl FromStr for Command {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (name, args) = s.split_once(' ').unwrap_or((s, ""));
+        let args = args.trim();
+        let (first, rest) = args.split_once(' ').unwrap_or((args, ""));
+        let cmd = match name {
+            "color" => {
+                Hsv::from_hex(first).ok_or_else(|| anyhow!("invalid color: {first}"))?;
+                Self::Color(first.to_string(), targets(rest))
+            }
+            "brightness" => Self::Brightness(parse_brightness(first)?, targets(rest)),
+            "on" => Self::Power(Power::On, targets(args)),
+            "off" => Self::Power(Power::Off, targets(args)),
+            "toggle" => Self::Power(Power::Toggle, targets(args)),
+            "select" => Self::Select(Selection::from_str(args, true).map_err(|e| anyhow!(e))?),
+            "discover" => Self::Discover,
+            "add" if !first.is_empty() => Self::Add(first.to_string(), rest.trim().to_string()),
+            "add" => return Err(anyhow!("usage: add <address> [name]")),
+            "refresh" => Self::Refresh,
+            "write" | "w" => Self::Write,
+            "" => return Err(anyhow!("empty command")),
+            _ => return Err(anyhow!("unknown command: {name}")),
+        };
+        Ok(cmd)
+    }
+}
+
+/// Splits comma separated device names.
+fn targets(s: &str) -> Vec<String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Parses brightness either in percent, like `40%`, or in range 0..1.
+pub fn parse_brightness(s: &str) -> Result<f32> {
+    let value = s
+        .strip_suffix('%')
+        .map_or_else(|| s.parse(), |p| p.parse::<f32>().map(|p| p / 100.0))
+        .with_context(|| format!("invalid brightness: {s}"))?;
+    if !(0.0..=1.0).contains(&value) {
+        return Err(anyhow!("brightness out of range: {s}"));
+    }
+    Ok(value)
+}
+
+/// Completes last word of command line from command names or `devices`.
+/// Returns completed line and candidates, if there is more than one.
+pub fn complete(line: &str, devices: &[&str]) -> (String, Vec<String>) {
+    let (name, args) = match line.split_once(' ') {
+        Some((name, args)) => (name, Some(args)),
+        None => (line, None),
+    };
+    // arguments of `select` command, as clap spells them
+    let selectors: Vec<String> = Selection::value_variants()
+        .iter()
+        .filter_map(ValueEnum::to_possible_value)
+        .map(|v| v.get_name().to_string())
+        .collect();
+    // start of word being completed, devices follow first argument of
+    // commands that take a value
+    let (head, word, candidates): (String, &str, Vec<&str>) = match (name, args) {
+        (_, None) => (String::new(), name, NAMES.to_vec()),
+        ("on" | "off" | "toggle", Some(args)) => {
+            let (done, word) = split_target(args);
+            (format!("{name} {done}"), word, devices.to_vec())
+        }
+        ("select", Some(args)) => {
+            let names = selectors.iter().map(String::as_str).collect();
+            (format!("{name} "), args, names)
+        }
+        ("color" | "brightness", Some(args)) => match args.split_once(' ') {
+            Some((value, targets)) => {
+                let (done, word) = split_target(targets);
+                (format!("{name} {value} {done}"), word, devices.to_vec())
+            }
+            None => return (line.to_string(), Vec::new()),
+        },
+        _ => return (line.to_string(), Vec::new()),
+    };
+
+    let lower = word.to_lowercase();
+    let matches: Vec<&str> = candidates
+        .into_iter()
+        .filter(|c| c.to_lowercase().starts_with(&lower))
+        .collect();
+    let completed = match matches.as_slice() {
+        [] => word.to_string(),
+        [only] if args.is_none() => format!("{only} "),
+        [only] => (*only).to_string(),
+        [first, rest @ ..] => {
+            let prefix: String = rest.iter().fold(first.to_string(), |prefix, m| {
+                prefix
+                    .chars()
+                    .zip(m.chars())
+                    .take_while(|(a, b)| a.eq_ignore_ascii_case(b))
+                    .map(|(a, _)| a)
+                    .collect()
+            });
+            if prefix.len() > word.len() {
+                prefix
+            } else {
+                word.to_string()
+            }
+        }
+    };
+    let hint = if matches.len() > 1 {
+        matches.into_iter().map(String::from).collect()
+    } else {
+        Vec::new()
+    };
+    (head + &completed, hint)
+}
+
+/// Splits comma separated targets into finished ones and the last one.
+fn split_target(targets: &str) -> (&str, &str) {
+    targets.rfind(',').map_or(("", targets), |i| {
+        let word = targets[i + 1..].trim_start();
+        (&targets[..targets.len() - word.len()], word)
+    })
+}
+
+/// Lines entered in command line, kept in a file across sessions.
+#[derive(Default)]
+pub struct History {
+    lines: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl History {
+    /// Loads history from file, missing file is treated as empty.
+    pub fn open(path: &Path) -> Result<Self> {
+        let lines = match fs::read_to_string(path) {
+            Ok(v) => v.lines().map(String::from).collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut history = Self {
+            lines,
+            path: Some(path.to_path_buf()),
+        };
+        if history.lines.len() > HISTORY_SIZE {
+            history.lines.drain(..history.lines.len() - HISTORY_SIZE);
+            history.save()?;
+        }
+        Ok(history)
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            fs::write(path, self.lines.join("\n") + "\n")?;
+        }
+        Ok(())
+    }
+
+    /// Adds line to history and appends it to file.
+    pub fn push(&mut self, line: &str) -> Result<()> {
+        if line.is_empty() || self.lines.last().is_some_and(|l| l == line) {
+            return Ok(());
+        }
+        self.lines.push(line.to_string());
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{line}")?;
+        }
+        Ok(())
+    }
+
+    pub const fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn get(&self, i: usize) -> Option<&str> {
+        self.lines.get(i).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEVICES: &[&str] = &["Desk", "desk lamp", "Hall", "10.0.0.2"];
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            "color #ff0000 desk, hall".parse::<Command>().unwrap(),
+            Command::Color("#ff0000".into(), vec!["desk".into(), "hall".into()])
+        );
+        assert_eq!(
+            " off  desk ".parse::<Command>().unwrap(),
+            Command::Power(Power::Off, vec!["desk".into()])
+        );
+        assert_eq!(
+            "toggle".parse::<Command>().unwrap(),
+            Command::Power(Power::Toggle, Vec::new())
+        );
+        assert_eq!(
+            "select filtered".parse::<Command>().unwrap(),
+            Command::Select(Selection::Filtered)
+        );
+        assert_eq!(
+            "add 10.0.0.3 lamp one".parse::<Command>().unwrap(),
+            Command::Add("10.0.0.3".into(), "lamp one".into())
+        );
+        assert_eq!("w".parse::<Command>().unwrap(), Command::Write);
+        let Command::Brightness(value, targets) = "brightness 40% hall".parse().unwrap() else {
+            panic!("expected brightness");
+        };
+        assert!((value - 0.4).abs() < f32::EPSILON);
+        assert_eq!(targets, ["hall"]);
+    }
+
+    #[test]
+    fn rejects_invalid_commands() {
+        for line in [
+            "",
+            "frobnicate",
+            "add",
+            "color red",
+            "brightness",
+            "brightness 150%",
+            "brightness 2",
+            "select some",
+        ] {
+            assert!(line.parse::<Command>().is_err(), "{line}");
+        }
+    }
+
+    #[test]
+    fn parses_brightness() {
+        assert!((parse_brightness("25%").unwrap() - 0.25).abs() < f32::EPSILON);
+        assert!((parse_brightness("0.5").unwrap() - 0.5).abs() < f32::EPSILON);
+        assert!(parse_brightness("-1%").is_err());
+        assert!(parse_brightness("half").is_err());
+    }
+
+    #[test]
+    fn completes_command_names() {
+        assert_eq!(complete("b", DEVICES), ("brightness ".into(), Vec::new()));
+        assert_eq!(
+            complete("o", DEVICES),
+            ("o".into(), vec!["off".into(), "on".into()])
+        );
+        assert_eq!(complete("x", DEVICES), ("x".into(), Vec::new()));
+    }
+
+    #[test]
+    fn completes_devices_to_common_prefix() {
+        assert_eq!(
+            complete("on de", DEVICES),
+            ("on Desk".into(), vec!["Desk".into(), "desk lamp".into()])
+        );
+        assert_eq!(
+            complete("on desk,ha", DEVICES),
+            ("on desk,Hall".into(), Vec::new())
+        );
+        assert_eq!(
+            complete("on desk, 10", DEVICES),
+            ("on desk, 10.0.0.2".into(), Vec::new())
+        );
+        assert_eq!(
+            complete("color #ff0000 H", DEVICES),
+            ("color #ff0000 Hall".into(), Vec::new())
+        );
+    }
+
+    #[test]
+    fn completes_selectors_and_skips_values() {
+        assert_eq!(
+            complete("select in", DEVICES),
+            ("select invert".into(), Vec::new())
+        );
+        assert_eq!(
+            complete("color #ff", DEVICES),
+            ("color #ff".into(), Vec::new())
+        );
+        assert_eq!(complete("add 10", DEVICES), ("add 10".into(), Vec::new()));
+    }
+}
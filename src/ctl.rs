@@ -0,0 +1,157 @@
+use std::{
+    fs::{self, DirBuilder},
+    io::{self, BufRead, BufReader, Write},
+    os::unix::{
+        fs::{DirBuilderExt, MetadataExt},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::cli::Cli;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Deserialize, Serialize)]
+enum Response {
+    Ok(Option<String>),
+    Err(String),
+}
+
+/// Control socket of running TUI instance. Connections are accepted and
+/// their requests read by worker threads, so that client that doesn't send
+/// anything can't block the UI. Socket file is removed on drop.
+pub struct Server {
+    requests: Receiver<(Connection, Result<Cli>)>,
+    path: PathBuf,
+}
+
+impl Server {
+    /// Starts listening on `path`. Returns `None` if other instance is already
+    /// listening there.
+    pub fn bind(path: &Path) -> Result<Option<Self>> {
+        let dir = path.parent().filter(|d| !d.as_os_str().is_empty());
+        if let Some(dir) = dir.filter(|d| !d.exists()) {
+            DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+        }
+        check_owner(path)?;
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                return Ok(None);
+            }
+            // leftover from instance that didn't exit cleanly
+            fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        let (tx, requests) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    let conn = Connection { stream };
+                    let req = conn.read_request();
+                    _ = tx.send((conn, req));
+                });
+            }
+        });
+        Ok(Some(Self {
+            requests,
+            path: path.to_path_buf(),
+        }))
+    }
+
+    /// Returns pending request and connection to respond on if there is
+    /// one, doesn't block.
+    pub fn accept(&self) -> Option<(Connection, Result<Cli>)> {
+        self.requests.try_recv().ok()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        _ = fs::remove_file(&self.path);
+    }
+}
+
+pub struct Connection {
+    stream: UnixStream,
+}
+
+impl Connection {
+    fn read_request(&self) -> Result<Cli> {
+        self.stream.set_read_timeout(Some(TIMEOUT))?;
+        self.stream.set_write_timeout(Some(TIMEOUT))?;
+        let mut line = String::new();
+        BufReader::new(&self.stream).read_line(&mut line)?;
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    pub fn respond(mut self, result: &Result<Option<String>>) -> Result<()> {
+        let resp = match result {
+            Ok(v) => Response::Ok(v.clone()),
+            Err(e) => Response::Err(e.to_string()),
+        };
+        serde_json::to_writer(&mut self.stream, &resp)?;
+        self.stream.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+/// Sends command to TUI instance listening on `path`. Returns `None` if there
+/// is no such instance.
+pub fn forward(path: &Path, cmd: &Cli) -> Option<Result<Option<String>>> {
+    if let Err(e) = check_owner(path) {
+        return Some(Err(e));
+    }
+    let mut stream = match UnixStream::connect(path) {
+        Ok(v) => v,
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            return Some(Err(e.into()));
+        }
+        Err(_) => return None,
+    };
+    Some(send(&mut stream, cmd))
+}
+
+/// Returns real user id of process.
+pub fn uid() -> u32 {
+    // SAFETY: getuid has no preconditions and always succeeds
+    unsafe { libc::getuid() }
+}
+
+/// Checks that socket at `path` can't be replaced by other users: directory
+/// containing it must be owned by current user or root, and socket itself,
+/// if it exists, by current user.
+fn check_owner(path: &Path) -> Result<()> {
+    let uid = uid();
+    if let Some(dir) = path.parent().filter(|d| d.exists()) {
+        let owner = fs::metadata(dir)?.uid();
+        if owner != uid && owner != 0 {
+            bail!("{} is owned by another user", dir.to_string_lossy());
+        }
+    }
+    match fs::symlink_metadata(path) {
+        Ok(m) if m.uid() != uid => bail!("{} is owned by another user", path.to_string_lossy()),
+        _ => Ok(()),
+    }
+}
+
+fn send(stream: &mut UnixStream, cmd: &Cli) -> Result<Option<String>> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    serde_json::to_writer(&mut *stream, cmd)?;
+    stream.write_all(b"\n")?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    match serde_json::from_str(&line)? {
+        Response::Ok(v) => Ok(v),
+        Response::Err(e) => Err(anyhow!(e)),
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+
+/// Replaces file with `contents` through temporary file, so that it is never
+/// left partially written. Previous version is kept as `<path>.1`, older ones
+/// are shifted up to `<path>.<backups>`. File that wouldn't change isn't
+/// written. Symlinks are followed and permissions of file are kept.
+pub fn write(path: &Path, contents: &str, backups: usize) -> Result<()> {
+    let path = &fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if fs::read_to_string(path).is_ok_and(|old| old == contents) {
+        return Ok(());
+    }
+    let tmp = with_suffix(path, "tmp");
+    let mut file = File::create(&tmp)?;
+    if let Ok(metadata) = fs::metadata(path) {
+        file.set_permissions(metadata.permissions())?;
+    }
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    if backups > 0 && path.exists() {
+        rotate(path, backups)?;
+    }
+    fs::rename(&tmp, path)?;
+    // rename itself is durable only after directory is synced
+    let dir = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+/// Shifts backups of file by one, dropping the oldest, and copies file to
+/// first one.
+fn rotate(path: &Path, backups: usize) -> Result<()> {
+    for i in (1..backups).rev() {
+        let from = with_suffix(path, &i.to_string());
+        if from.exists() {
+            fs::rename(&from, with_suffix(path, &(i + 1).to_string()))?;
+        }
+    }
+    fs::copy(path, with_suffix(path, "1"))?;
+    Ok(())
+}
+
+/// Returns `path` with `.<suffix>` appended to file name.
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    name.into()
+}
+
+/// Advisory lock of file, held until dropped. Lock is taken on separate
+/// `<path>.lock` file, as the file itself is replaced on every write.
+pub struct Lock {
+    _file: File,
+}
+
+impl Lock {
+    /// Locks file, returns `None` if another process holds the lock.
+    pub fn acquire(path: &Path) -> Result<Option<Self>> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(with_suffix(path, "lock"))?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Self { _file: file })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::{symlink, PermissionsExt};
+
+    /// Returns empty directory unique to test.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bulbs-tui-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn rotates_backups() {
+        let dir = test_dir("rotate");
+        let path = dir.join("config.toml");
+        for contents in ["a", "b", "c", "d"] {
+            write(&path, contents, 2).unwrap();
+        }
+        assert_eq!(read(&path), "d");
+        assert_eq!(read(&with_suffix(&path, "1")), "c");
+        assert_eq!(read(&with_suffix(&path, "2")), "b");
+        assert!(!with_suffix(&path, "3").exists());
+        assert!(!with_suffix(&path, "tmp").exists());
+
+        // unchanged contents don't push out a backup
+        write(&path, "d", 2).unwrap();
+        assert_eq!(read(&with_suffix(&path, "1")), "c");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_no_backups() {
+        let dir = test_dir("no-backups");
+        let path = dir.join("config.toml");
+        write(&path, "a", 0).unwrap();
+        write(&path, "b", 0).unwrap();
+        assert_eq!(read(&path), "b");
+        assert!(!with_suffix(&path, "1").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn follows_symlink_and_keeps_permissions() {
+        let dir = test_dir("symlink");
+        let target = dir.join("real.toml");
+        let link = dir.join("config.toml");
+        fs::write(&target, "a").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o600)).unwrap();
+        symlink(&target, &link).unwrap();
+
+        write(&link, "b", 1).unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(read(&target), "b");
+        assert_eq!(read(&with_suffix(&target, "1")), "a");
+        let mode = fs::metadata(&target).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,79 @@
+use serde_json::{json, Value};
+use std::fmt::Write;
+
+use crate::api::{Bulb, Device};
+
+const DISCOVERY_PREFIX: &str = "homeassistant";
+const TOPIC_PREFIX: &str = "bulbs";
+
+/// Returns MQTT object id of device, derived from its address because name is
+/// optional and not guaranteed to be unique.
+fn object_id(device: &Device) -> String {
+    device
+        .ip
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Returns Home Assistant discovery topic and payload (JSON schema light) for
+/// device.
+pub fn discovery(device: &Device) -> (String, Value) {
+    let id = object_id(device);
+    let name = if device.name.is_empty() {
+        &device.ip
+    } else {
+        &device.name
+    };
+    let payload = json!({
+        "name": name,
+        "unique_id": format!("bulbs_{id}"),
+        "schema": "json",
+        "command_topic": format!("{TOPIC_PREFIX}/{id}/set"),
+        "state_topic": format!("{TOPIC_PREFIX}/{id}/state"),
+        "brightness": true,
+        "brightness_scale": 255,
+        "supported_color_modes": ["rgb"],
+        "device": {
+            "identifiers": [format!("bulbs_{id}")],
+            "name": name,
+            "manufacturer": "bulbs",
+            "configuration_url": device.url("/led"),
+        },
+    });
+    (format!("{DISCOVERY_PREFIX}/light/{id}/config"), payload)
+}
+
+/// Returns state topic and payload of device in format expected by discovery
+/// config from [`discovery`].
+pub fn state(device: &Device) -> (String, Value) {
+    let (r, g, b) = device.bulb.rgb();
+    let payload = json!({
+        "state": if device.bulb.enabled == 1 { "ON" } else { "OFF" },
+        "brightness": brightness(&device.bulb),
+        "color_mode": "rgb",
+        "color": { "r": r, "g": g, "b": b },
+    });
+    (
+        format!("{TOPIC_PREFIX}/{}/state", object_id(device)),
+        payload,
+    )
+}
+
+/// Maps brightness from range 0..1 used by bulbs to 0..255 used by Home Assistant.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn brightness(bulb: &Bulb) -> u8 {
+    (bulb.brightness.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Returns discovery and state messages for given devices, one
+/// `<topic> <payload>` pair per line, ready to be fed to an MQTT client.
+pub fn messages<'a>(devices: impl IntoIterator<Item = &'a Device>) -> String {
+    let mut out = String::new();
+    for dev in devices {
+        for (topic, payload) in [discovery(dev), state(dev)] {
+            _ = writeln!(out, "{topic} {payload}");
+        }
+    }
+    out
+}
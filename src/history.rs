@@ -0,0 +1,49 @@
+use crate::api::Bulb;
+
+/// Maximum number of changes that can be undone.
+const DEPTH: usize = 100;
+
+/// State of devices, identified by address.
+pub type Snapshot = Vec<(String, Bulb)>;
+
+/// Undo and redo stacks of device state changes.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+
+impl History {
+    /// Records state of devices from before new change, discarding changes that
+    /// could be redone.
+    pub fn record(&mut self, before: Snapshot) {
+        if before.is_empty() {
+            return;
+        }
+        if self.undo.len() == DEPTH {
+            self.undo.remove(0);
+        }
+        self.undo.push(before);
+        self.redo.clear();
+    }
+
+    pub fn pop_undo(&mut self) -> Option<Snapshot> {
+        self.undo.pop()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Snapshot> {
+        self.redo.pop()
+    }
+
+    /// Records state that can be redone: state from before undo, or state of
+    /// redo that failed.
+    pub fn push_redo(&mut self, state: Snapshot) {
+        self.redo.push(state);
+    }
+
+    /// Records state that can be undone: state from before redo, or state of
+    /// undo that failed.
+    pub fn push_undo(&mut self, state: Snapshot) {
+        self.undo.push(state);
+    }
+}
@@ -0,0 +1,220 @@
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Single line text field with cursor, validated as it is edited.
+#[derive(Default)]
+pub struct Input {
+    value: String,
+    /// Cursor position, in characters.
+    cursor: usize,
+    validate: Option<fn(&str) -> Result<()>>,
+}
+
+impl Input {
+    pub fn new(validate: fn(&str) -> Result<()>) -> Self {
+        Self {
+            validate: Some(validate),
+            ..Self::default()
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub const fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Replaces value, cursor is moved to its end.
+    pub fn set(&mut self, value: impl Into<String>) {
+        self.value = value.into();
+        self.cursor = self.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.value.clear();
+        self.cursor = 0;
+    }
+
+    /// Returns why value is invalid, empty value isn't checked.
+    pub fn error(&self) -> Option<String> {
+        if self.value.is_empty() {
+            return None;
+        }
+        self.validate?(&self.value).err().map(|e| e.to_string())
+    }
+
+    /// Edits value or moves cursor, returns true if value changed.
+    pub fn handle_key(&mut self, key: &KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('w') if ctrl => self.delete_word(),
+            KeyCode::Char(c) if !ctrl && !key.modifiers.contains(KeyModifiers::ALT) => {
+                self.value.insert(self.byte_index(self.cursor), c);
+                self.cursor += 1;
+                true
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.value.remove(self.byte_index(self.cursor));
+                true
+            }
+            KeyCode::Delete if self.cursor < self.len() => {
+                self.value.remove(self.byte_index(self.cursor));
+                true
+            }
+            KeyCode::Left => {
+                self.cursor = self.cursor.saturating_sub(1);
+                false
+            }
+            KeyCode::Right => {
+                self.cursor = (self.cursor + 1).min(self.len());
+                false
+            }
+            KeyCode::Home => {
+                self.cursor = 0;
+                false
+            }
+            KeyCode::End => {
+                self.cursor = self.len();
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Inserts pasted text at cursor, line breaks and other control
+    /// characters are dropped.
+    pub fn paste(&mut self, text: &str) {
+        let text = single_line(text);
+        self.value.insert_str(self.byte_index(self.cursor), &text);
+        self.cursor += text.chars().count();
+    }
+
+    /// Deletes word before cursor, along with spaces following it.
+    fn delete_word(&mut self) -> bool {
+        let end = self.byte_index(self.cursor);
+        let before = self.value[..end].trim_end();
+        let start = before.rfind(char::is_whitespace).map_or(0, |i| {
+            i + before[i..].chars().next().map_or(1, char::len_utf8)
+        });
+        if start == end {
+            return false;
+        }
+        self.cursor -= self.value[start..end].chars().count();
+        self.value.replace_range(start..end, "");
+        true
+    }
+
+    fn len(&self) -> usize {
+        self.value.chars().count()
+    }
+
+    fn byte_index(&self, cursor: usize) -> usize {
+        self.value
+            .char_indices()
+            .nth(cursor)
+            .map_or(self.value.len(), |(i, _)| i)
+    }
+}
+
+/// Returns pasted text without line breaks and other control characters.
+pub fn single_line(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(input: &mut Input, code: KeyCode) -> bool {
+        input.handle_key(&KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn ctrl_w(input: &mut Input) -> bool {
+        input.handle_key(&KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL))
+    }
+
+    #[test]
+    fn deletes_word_before_cursor() {
+        let mut input = Input::default();
+        input.set("on desk lamp  ");
+        assert!(ctrl_w(&mut input));
+        assert_eq!(input.value(), "on desk ");
+        assert!(ctrl_w(&mut input));
+        assert_eq!(input.value(), "on ");
+        assert!(ctrl_w(&mut input));
+        assert_eq!(input.value(), "");
+        assert!(!ctrl_w(&mut input));
+    }
+
+    #[test]
+    fn deletes_word_in_middle() {
+        let mut input = Input::default();
+        input.set("color #ff0000 desk");
+        for _ in 0.." desk".len() {
+            press(&mut input, KeyCode::Left);
+        }
+        assert!(ctrl_w(&mut input));
+        assert_eq!(input.value(), "color  desk");
+        assert_eq!(input.cursor(), "color ".len());
+    }
+
+    #[test]
+    fn deletes_multibyte_word() {
+        let mut input = Input::default();
+        input.set("lampa żółć");
+        assert!(ctrl_w(&mut input));
+        assert_eq!(input.value(), "lampa ");
+        assert_eq!(input.cursor(), 6);
+        input.set("a\u{3000}ąę");
+        assert!(ctrl_w(&mut input));
+        assert_eq!(input.value(), "a\u{3000}");
+        assert_eq!(input.cursor(), 2);
+    }
+
+    #[test]
+    fn edits_at_cursor_counted_in_characters() {
+        let mut input = Input::default();
+        input.set("żółw");
+        assert_eq!(input.cursor(), 4);
+        press(&mut input, KeyCode::Left);
+        press(&mut input, KeyCode::Left);
+        assert!(press(&mut input, KeyCode::Backspace));
+        assert_eq!(input.value(), "żłw");
+        assert!(press(&mut input, KeyCode::Char('ó')));
+        assert_eq!(input.value(), "żółw");
+        assert!(press(&mut input, KeyCode::Delete));
+        assert_eq!(input.value(), "żów");
+        assert_eq!(input.cursor(), 2);
+        press(&mut input, KeyCode::End);
+        assert!(!press(&mut input, KeyCode::Delete));
+        press(&mut input, KeyCode::Right);
+        assert_eq!(input.cursor(), 3);
+        press(&mut input, KeyCode::Home);
+        assert!(!press(&mut input, KeyCode::Backspace));
+        assert!(press(&mut input, KeyCode::Char('ü')));
+        assert_eq!(input.value(), "üżów");
+    }
+
+    #[test]
+    fn pastes_single_line_at_cursor() {
+        let mut input = Input::default();
+        input.set("ab");
+        press(&mut input, KeyCode::Left);
+        input.paste("ż\nó\tł");
+        assert_eq!(input.value(), "ażółb");
+        assert_eq!(input.cursor(), 4);
+    }
+
+    #[test]
+    fn validates_only_non_empty_value() {
+        let mut input = Input::new(|v| v.parse::<u8>().map(drop).map_err(Into::into));
+        assert_eq!(input.error(), None);
+        input.set("12");
+        assert_eq!(input.error(), None);
+        input.set("x");
+        assert!(input.error().is_some());
+    }
+}
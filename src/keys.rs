@@ -0,0 +1,596 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use anyhow::{anyhow, Context as _, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Part of UI key bindings apply to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Context {
+    Devices,
+    Logs,
+    Search,
+    AddDevice,
+    Settings,
+    /// Hue bar and saturation/value grid of device settings.
+    Picker,
+    /// Command line opened with `:`.
+    Command,
+    /// Yes/no prompt, like confirmation of device removal.
+    Confirm,
+    Help,
+}
+
+impl Context {
+    /// Contexts listed in help overlay, in order.
+    pub const ALL: [Self; 8] = [
+        Self::Devices,
+        Self::Search,
+        Self::Command,
+        Self::Confirm,
+        Self::Logs,
+        Self::AddDevice,
+        Self::Settings,
+        Self::Picker,
+    ];
+
+    pub const fn title(self) -> &'static str {
+        match self {
+            Self::Devices => "Devices",
+            Self::Search => "Filter and search prompts",
+            Self::Logs => "Logs",
+            Self::AddDevice => "Add or edit device",
+            Self::Settings => "Device settings",
+            Self::Picker => "Color picker",
+            Self::Command => "Command line",
+            Self::Confirm => "Confirmation prompts",
+            Self::Help => "Help",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(
+    rename_all = "snake_case",
+    expecting = "unknown action, expected name of action like `quit` or `brightness_level_5`"
+)]
+pub enum Action {
+    Quit,
+    SwitchPane,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    ToggleCurrent,
+    ToggleSelected,
+    Select,
+    SelectAll,
+    SelectNone,
+    InvertSelection,
+    /// Select devices that are on, deselect the rest.
+    SelectOn,
+    /// Select devices that are off, deselect the rest.
+    SelectOff,
+    /// Select devices left visible by filter, deselect the rest.
+    SelectFiltered,
+    Add,
+    Edit,
+    Discover,
+    Settings,
+    Remove,
+    /// Move device under cursor up in config order.
+    MoveUp,
+    /// Move device under cursor down in config order.
+    MoveDown,
+    /// Bring back device removed last.
+    Restore,
+    Refresh,
+    Undo,
+    Redo,
+    BrightnessUp,
+    BrightnessDown,
+    HueLeft,
+    HueRight,
+    Sort,
+    ReverseSort,
+    Details,
+    Help,
+    /// Open command line.
+    Command,
+    ClearLogs,
+    Search,
+    NextMatch,
+    PrevMatch,
+    CycleLevel,
+    FilterDevice,
+    Apply,
+    Cancel,
+    NextField,
+    /// Complete command or device name in command line.
+    Complete,
+    /// Toggle sending edited settings to devices before applying.
+    Preview,
+    /// Set brightness of selected devices to given tenths, bound in config
+    /// as `brightness_level_<n>`.
+    #[serde(
+        untagged,
+        serialize_with = "serialize_level",
+        deserialize_with = "deserialize_level"
+    )]
+    BrightnessLevel(u8),
+}
+
+// signature is dictated by serde
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn serialize_level<S: Serializer>(level: &u8, s: S) -> Result<S::Ok, S::Error> {
+    s.collect_str(&format_args!("brightness_level_{level}"))
+}
+
+fn deserialize_level<'de, D: Deserializer<'de>>(d: D) -> Result<u8, D::Error> {
+    let name = String::deserialize(d)?;
+    name.strip_prefix("brightness_level_")
+        .and_then(|n| n.parse().ok())
+        .filter(|n| (1..=10).contains(n))
+        .ok_or_else(|| serde::de::Error::custom(format!("unknown action: {name}")))
+}
+
+/// Key bindings from config file, keys bound to action replace its defaults.
+pub type KeysConfig = BTreeMap<Context, BTreeMap<Action, Vec<String>>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    const fn new(code: KeyCode) -> Self {
+        Self {
+            code,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    const fn char(c: char) -> Self {
+        Self::new(KeyCode::Char(c))
+    }
+
+    const fn ctrl(c: char) -> Self {
+        Self {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::CONTROL,
+        }
+    }
+
+    /// Returns true if key is pressed in `event`. Shift is ignored because it
+    /// is already reflected in case of character.
+    fn matches(&self, event: &KeyEvent) -> bool {
+        let modifiers = event.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+        self.code == event.code && self.modifiers == modifiers
+    }
+}
+
+impl FromStr for Key {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut name = s;
+        loop {
+            if let Some(rest) = name.strip_prefix("ctrl-") {
+                modifiers |= KeyModifiers::CONTROL;
+                name = rest;
+            } else if let Some(rest) = name.strip_prefix("alt-") {
+                modifiers |= KeyModifiers::ALT;
+                name = rest;
+            } else {
+                break;
+            }
+        }
+
+        let mut chars = name.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match name.to_ascii_lowercase().as_str() {
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "space" => KeyCode::Char(' '),
+                "backspace" => KeyCode::Backspace,
+                "delete" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "pgup" | "pageup" => KeyCode::PageUp,
+                "pgdn" | "pagedown" => KeyCode::PageDown,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                f => f
+                    .strip_prefix('f')
+                    .and_then(|n| n.parse().ok())
+                    .map(KeyCode::F)
+                    .ok_or_else(|| anyhow!("unknown key: {s}"))?,
+            },
+        };
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "^")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "M-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "<space>"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::Enter => write!(f, "<enter>"),
+            KeyCode::Esc => write!(f, "<esc>"),
+            KeyCode::Tab => write!(f, "<tab>"),
+            KeyCode::BackTab => write!(f, "<backtab>"),
+            KeyCode::Backspace => write!(f, "<backspace>"),
+            KeyCode::Delete => write!(f, "<delete>"),
+            KeyCode::Insert => write!(f, "<insert>"),
+            KeyCode::Up => write!(f, "<up>"),
+            KeyCode::Down => write!(f, "<down>"),
+            KeyCode::Left => write!(f, "<left>"),
+            KeyCode::Right => write!(f, "<right>"),
+            KeyCode::PageUp => write!(f, "<pgup>"),
+            KeyCode::PageDown => write!(f, "<pgdn>"),
+            KeyCode::Home => write!(f, "<home>"),
+            KeyCode::End => write!(f, "<end>"),
+            KeyCode::F(n) => write!(f, "<f{n}>"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+const DEFAULTS: &[(Context, Action, &[Key])] = {
+    use Action as A;
+    use Context as C;
+    use KeyCode as K;
+    &[
+        (C::Devices, A::Quit, &[Key::char('q'), Key::new(K::Esc)]),
+        (C::Devices, A::ToggleCurrent, &[Key::new(K::Enter)]),
+        (C::Devices, A::SwitchPane, &[Key::new(K::Tab)]),
+        (C::Devices, A::Up, &[Key::char('k'), Key::new(K::Up)]),
+        (C::Devices, A::Down, &[Key::char('j'), Key::new(K::Down)]),
+        (C::Devices, A::Add, &[Key::char('a')]),
+        (C::Devices, A::Edit, &[Key::char('E')]),
+        (C::Devices, A::Discover, &[Key::char('A')]),
+        (C::Devices, A::Settings, &[Key::char('c')]),
+        (C::Devices, A::Remove, &[Key::char('d')]),
+        (C::Devices, A::Restore, &[Key::char('p')]),
+        (C::Devices, A::MoveUp, &[Key::char('K')]),
+        (C::Devices, A::MoveDown, &[Key::char('J')]),
+        (C::Devices, A::ToggleSelected, &[Key::char('e')]),
+        (C::Devices, A::Refresh, &[Key::char('r')]),
+        (C::Devices, A::Undo, &[Key::char('u')]),
+        (C::Devices, A::Redo, &[Key::ctrl('r')]),
+        (C::Devices, A::Select, &[Key::char(' ')]),
+        (C::Devices, A::SelectAll, &[Key::ctrl('a')]),
+        (C::Devices, A::SelectNone, &[Key::ctrl('n')]),
+        (C::Devices, A::InvertSelection, &[Key::char('*')]),
+        (C::Devices, A::SelectOn, &[Key::char('o')]),
+        (C::Devices, A::SelectOff, &[Key::char('O')]),
+        (C::Devices, A::SelectFiltered, &[Key::char('F')]),
+        (C::Devices, A::Search, &[Key::char('/')]),
+        (C::Devices, A::Sort, &[Key::char('s')]),
+        (C::Devices, A::ReverseSort, &[Key::char('S')]),
+        (C::Devices, A::Details, &[Key::char('i')]),
+        (C::Devices, A::Help, &[Key::char('?')]),
+        (C::Devices, A::Command, &[Key::char(':')]),
+        (
+            C::Devices,
+            A::BrightnessUp,
+            &[Key::char('+'), Key::char('=')],
+        ),
+        (C::Devices, A::BrightnessDown, &[Key::char('-')]),
+        (C::Devices, A::HueLeft, &[Key::char('[')]),
+        (C::Devices, A::HueRight, &[Key::char(']')]),
+        (C::Devices, A::BrightnessLevel(1), &[Key::char('1')]),
+        (C::Devices, A::BrightnessLevel(2), &[Key::char('2')]),
+        (C::Devices, A::BrightnessLevel(3), &[Key::char('3')]),
+        (C::Devices, A::BrightnessLevel(4), &[Key::char('4')]),
+        (C::Devices, A::BrightnessLevel(5), &[Key::char('5')]),
+        (C::Devices, A::BrightnessLevel(6), &[Key::char('6')]),
+        (C::Devices, A::BrightnessLevel(7), &[Key::char('7')]),
+        (C::Devices, A::BrightnessLevel(8), &[Key::char('8')]),
+        (C::Devices, A::BrightnessLevel(9), &[Key::char('9')]),
+        (C::Devices, A::BrightnessLevel(10), &[Key::char('0')]),
+        (C::Logs, A::Quit, &[Key::char('q'), Key::new(K::Esc)]),
+        (C::Logs, A::ClearLogs, &[Key::new(K::Backspace)]),
+        (C::Logs, A::SwitchPane, &[Key::new(K::Tab)]),
+        (C::Logs, A::Left, &[Key::char('h'), Key::new(K::Left)]),
+        (C::Logs, A::Right, &[Key::char('l'), Key::new(K::Right)]),
+        (C::Logs, A::Up, &[Key::char('k'), Key::new(K::Up)]),
+        (C::Logs, A::Down, &[Key::char('j'), Key::new(K::Down)]),
+        (C::Logs, A::PageUp, &[Key::new(K::PageUp)]),
+        (C::Logs, A::PageDown, &[Key::new(K::PageDown)]),
+        (C::Logs, A::Top, &[Key::char('g'), Key::new(K::Home)]),
+        (C::Logs, A::Bottom, &[Key::char('G'), Key::new(K::End)]),
+        (C::Logs, A::Search, &[Key::char('/')]),
+        (C::Logs, A::NextMatch, &[Key::char('n')]),
+        (C::Logs, A::PrevMatch, &[Key::char('N')]),
+        (C::Logs, A::CycleLevel, &[Key::char('v')]),
+        (C::Logs, A::FilterDevice, &[Key::char('f')]),
+        (C::Logs, A::Help, &[Key::char('?')]),
+        (C::Logs, A::Command, &[Key::char(':')]),
+        (C::Search, A::Apply, &[Key::new(K::Enter)]),
+        (C::Search, A::Cancel, &[Key::new(K::Esc)]),
+        (C::Confirm, A::Apply, &[Key::char('y'), Key::new(K::Enter)]),
+        (
+            C::Confirm,
+            A::Cancel,
+            &[Key::char('n'), Key::new(K::Esc), Key::char('q')],
+        ),
+        (C::Command, A::Apply, &[Key::new(K::Enter)]),
+        (C::Command, A::Cancel, &[Key::new(K::Esc)]),
+        (C::Command, A::Complete, &[Key::new(K::Tab)]),
+        (C::Command, A::Up, &[Key::new(K::Up)]),
+        (C::Command, A::Down, &[Key::new(K::Down)]),
+        (C::AddDevice, A::Apply, &[Key::new(K::Enter)]),
+        (C::AddDevice, A::Cancel, &[Key::new(K::Esc)]),
+        (
+            C::AddDevice,
+            A::NextField,
+            &[Key::new(K::Tab), Key::new(K::Up), Key::new(K::Down)],
+        ),
+        (C::Settings, A::Apply, &[Key::new(K::Enter)]),
+        (C::Settings, A::Cancel, &[Key::new(K::Esc), Key::char('q')]),
+        (
+            C::Settings,
+            A::NextField,
+            &[Key::new(K::Tab), Key::new(K::Up), Key::new(K::Down)],
+        ),
+        (C::Settings, A::Preview, &[Key::ctrl('p')]),
+        (C::Picker, A::Apply, &[Key::new(K::Enter)]),
+        (C::Picker, A::Cancel, &[Key::new(K::Esc), Key::char('q')]),
+        (C::Picker, A::NextField, &[Key::new(K::Tab)]),
+        (C::Picker, A::Preview, &[Key::ctrl('p')]),
+        (C::Help, A::Up, &[Key::char('k'), Key::new(K::Up)]),
+        (C::Help, A::Down, &[Key::char('j'), Key::new(K::Down)]),
+        (C::Help, A::PageUp, &[Key::new(K::PageUp)]),
+        (C::Help, A::PageDown, &[Key::new(K::PageDown)]),
+        (C::Help, A::Top, &[Key::char('g'), Key::new(K::Home)]),
+        (C::Help, A::Bottom, &[Key::char('G'), Key::new(K::End)]),
+        (
+            C::Help,
+            A::Cancel,
+            &[Key::new(K::Esc), Key::char('q'), Key::char('?')],
+        ),
+        (C::Picker, A::Left, &[Key::char('h'), Key::new(K::Left)]),
+        (C::Picker, A::Right, &[Key::char('l'), Key::new(K::Right)]),
+        (C::Picker, A::Up, &[Key::char('k'), Key::new(K::Up)]),
+        (C::Picker, A::Down, &[Key::char('j'), Key::new(K::Down)]),
+    ]
+};
+
+/// Entries of help overlay: description and actions whose keys are shown.
+/// Entries marked `true` are also shown in help bar, with first key of each
+/// action only.
+const HELP: &[(Context, &str, &[Action], bool)] = {
+    use Action as A;
+    use Context as C;
+    &[
+        (C::Devices, "Move cursor", &[A::Down, A::Up], false),
+        (C::Devices, "Move device", &[A::MoveDown, A::MoveUp], false),
+        (C::Devices, "Add device", &[A::Add], true),
+        (C::Devices, "Edit", &[A::Edit], true),
+        (C::Devices, "Autodetect", &[A::Discover], true),
+        (C::Devices, "Change color", &[A::Settings], true),
+        (C::Devices, "Remove", &[A::Remove], true),
+        (C::Devices, "Restore removed", &[A::Restore], false),
+        (C::Devices, "ON/OFF", &[A::ToggleSelected], true),
+        (C::Devices, "Refresh", &[A::Refresh], true),
+        (C::Devices, "Undo/Redo", &[A::Undo, A::Redo], true),
+        (
+            C::Devices,
+            "Brightness",
+            &[A::BrightnessDown, A::BrightnessUp],
+            true,
+        ),
+        (C::Devices, "Hue", &[A::HueLeft, A::HueRight], true),
+        (
+            C::Devices,
+            "Brightness 10%-100%",
+            &[
+                A::BrightnessLevel(1),
+                A::BrightnessLevel(2),
+                A::BrightnessLevel(3),
+                A::BrightnessLevel(4),
+                A::BrightnessLevel(5),
+                A::BrightnessLevel(6),
+                A::BrightnessLevel(7),
+                A::BrightnessLevel(8),
+                A::BrightnessLevel(9),
+                A::BrightnessLevel(10),
+            ],
+            false,
+        ),
+        (C::Devices, "ON/OFF (one)", &[A::ToggleCurrent], true),
+        (C::Devices, "Select", &[A::Select], true),
+        (
+            C::Devices,
+            "Select all/none",
+            &[A::SelectAll, A::SelectNone],
+            false,
+        ),
+        (C::Devices, "Invert selection", &[A::InvertSelection], false),
+        (
+            C::Devices,
+            "Select those on/off",
+            &[A::SelectOn, A::SelectOff],
+            false,
+        ),
+        (C::Devices, "Select filtered", &[A::SelectFiltered], false),
+        (C::Devices, "Filter", &[A::Search], true),
+        (C::Devices, "Sort", &[A::Sort, A::ReverseSort], true),
+        (C::Devices, "Details", &[A::Details], true),
+        (C::Devices, "Switch to logs", &[A::SwitchPane], false),
+        (C::Devices, "Command", &[A::Command], true),
+        (C::Devices, "Help", &[A::Help], true),
+        (C::Devices, "Quit", &[A::Quit], true),
+        (C::Search, "Apply filter", &[A::Apply], false),
+        (C::Search, "Clear filter", &[A::Cancel], false),
+        (C::Confirm, "Yes", &[A::Apply], true),
+        (C::Confirm, "No", &[A::Cancel], true),
+        (C::Command, "Run", &[A::Apply], true),
+        (C::Command, "Cancel", &[A::Cancel], true),
+        (C::Command, "Complete", &[A::Complete], true),
+        (C::Command, "History", &[A::Up, A::Down], true),
+        (
+            C::Logs,
+            "Scroll",
+            &[A::Down, A::Up, A::PageUp, A::PageDown, A::Top, A::Bottom],
+            true,
+        ),
+        (C::Logs, "Scroll sideways", &[A::Left, A::Right], false),
+        (C::Logs, "Search", &[A::Search], true),
+        (C::Logs, "Next/Prev", &[A::NextMatch, A::PrevMatch], true),
+        (C::Logs, "Level", &[A::CycleLevel], true),
+        (C::Logs, "Device", &[A::FilterDevice], true),
+        (C::Logs, "Clear", &[A::ClearLogs], true),
+        (C::Logs, "Switch to devices", &[A::SwitchPane], false),
+        (C::Logs, "Command", &[A::Command], true),
+        (C::Logs, "Help", &[A::Help], true),
+        (C::Logs, "Quit", &[A::Quit], true),
+        (C::AddDevice, "Apply", &[A::Apply], true),
+        (C::AddDevice, "Cancel", &[A::Cancel], true),
+        (C::AddDevice, "Next field", &[A::NextField], false),
+        (C::Settings, "Apply", &[A::Apply], true),
+        (C::Settings, "Cancel", &[A::Cancel], true),
+        (C::Settings, "Next field", &[A::NextField], false),
+        (C::Settings, "Live", &[A::Preview], true),
+        (
+            C::Picker,
+            "Move",
+            &[A::Left, A::Down, A::Up, A::Right],
+            true,
+        ),
+        (C::Picker, "Next field", &[A::NextField], true),
+        (C::Picker, "Apply", &[A::Apply], true),
+        (C::Picker, "Cancel", &[A::Cancel], true),
+        (C::Picker, "Live", &[A::Preview], true),
+        (
+            C::Help,
+            "Scroll",
+            &[A::Down, A::Up, A::PageUp, A::PageDown, A::Top, A::Bottom],
+            true,
+        ),
+        (C::Help, "Close", &[A::Cancel], true),
+    ]
+};
+
+pub struct Keymap {
+    bindings: BTreeMap<Context, Vec<(Key, Action)>>,
+}
+
+impl Keymap {
+    pub fn new(config: &KeysConfig) -> Result<Self> {
+        let mut bindings: BTreeMap<Context, Vec<(Key, Action)>> = BTreeMap::new();
+        // configured keys go first, so they take precedence over defaults
+        for (ctx, actions) in config {
+            for (action, keys) in actions {
+                for k in keys {
+                    let key = k
+                        .parse()
+                        .with_context(|| format!("invalid key binding for {action:?}"))?;
+                    bindings.entry(*ctx).or_default().push((key, *action));
+                }
+            }
+        }
+        for (ctx, action, keys) in DEFAULTS {
+            if config.get(ctx).is_some_and(|c| c.contains_key(action)) {
+                continue;
+            }
+            for key in *keys {
+                bindings.entry(*ctx).or_default().push((*key, *action));
+            }
+        }
+        Ok(Self { bindings })
+    }
+
+    /// Returns action bound to key pressed in `event`.
+    pub fn action(&self, ctx: Context, event: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .get(&ctx)?
+            .iter()
+            .find(|(key, _)| key.matches(event))
+            .map(|(_, action)| *action)
+    }
+
+    fn keys(&self, ctx: Context, action: Action) -> impl Iterator<Item = Key> + '_ {
+        self.bindings
+            .get(&ctx)
+            .into_iter()
+            .flatten()
+            .filter(move |(_, a)| *a == action)
+            .map(|(key, _)| *key)
+    }
+
+    /// Returns labels and keys of help bar entries.
+    pub fn help(&self, ctx: Context) -> Vec<(&'static str, String)> {
+        HELP.iter()
+            .filter(|(c, _, _, bar)| *c == ctx && *bar)
+            .filter_map(|(_, label, actions, _)| {
+                let keys: Vec<String> = actions
+                    .iter()
+                    .filter_map(|a| self.keys(ctx, *a).next())
+                    .map(|k| k.to_string())
+                    .collect();
+                (!keys.is_empty()).then(|| (*label, keys.join("/")))
+            })
+            .collect()
+    }
+
+    /// Returns descriptions and all keys of help overlay entries.
+    pub fn overlay(&self, ctx: Context) -> Vec<(&'static str, String)> {
+        HELP.iter()
+            .filter(|(c, _, _, _)| *c == ctx)
+            .filter_map(|(_, label, actions, _)| {
+                let keys: Vec<String> = actions
+                    .iter()
+                    .flat_map(|a| self.keys(ctx, *a))
+                    .map(|k| k.to_string())
+                    .collect();
+                (!keys.is_empty()).then(|| (*label, keys.join(" ")))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn brightness_level_round_trips_through_config() {
+        let text = "[devices]\nquit = [\"x\"]\nbrightness_level_3 = [\"3\"]\n";
+        let config: KeysConfig = toml::from_str(text).unwrap();
+        let devices = &config[&Context::Devices];
+        assert_eq!(devices[&Action::BrightnessLevel(3)], ["3"]);
+        assert_eq!(devices[&Action::Quit], ["x"]);
+        assert_eq!(toml::to_string(&config).unwrap(), text);
+    }
+
+    #[test]
+    fn unknown_action_is_rejected() {
+        for name in ["brightness_level_11", "brightness_level_0", "nope"] {
+            let text = format!("[devices]\n{name} = [\"3\"]\n");
+            let err = toml::from_str::<KeysConfig>(&text).unwrap_err();
+            assert!(err.to_string().contains("unknown action"), "{err}");
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+
+/// Number of entries kept in memory.
+const CAPACITY: usize = 1000;
+/// Size after which log file is rotated.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+/// Number of rotated log files kept.
+const ROTATED_FILES: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl Level {
+    /// Returns next, more severe level, wrapping around to least severe one.
+    pub const fn cycle(self) -> Self {
+        match self {
+            Self::Debug => Self::Info,
+            Self::Info => Self::Warn,
+            Self::Warn => Self::Error,
+            Self::Error => Self::Debug,
+        }
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "ERROR",
+            Self::Warn => "WARN",
+            Self::Info => "INFO",
+            Self::Debug => "DEBUG",
+        }
+    }
+}
+
+pub struct Entry {
+    /// Sequence number of entry in log, assigned when it is pushed.
+    pub id: u64,
+    pub time: SystemTime,
+    pub level: Level,
+    /// Address of device entry is about, if any.
+    pub device: Option<String>,
+    pub message: String,
+}
+
+impl Entry {
+    pub fn new(level: Level, device: Option<String>, message: String) -> Self {
+        Self {
+            id: 0,
+            time: SystemTime::now(),
+            level,
+            device,
+            message,
+        }
+    }
+
+    /// Returns time of entry as `HH:MM:SS`, in local time.
+    pub fn time_of_day(&self) -> String {
+        let t = local_rfc3339(self.time);
+        t.get(11..19).unwrap_or(&t).to_string()
+    }
+
+    /// Formats entry without timestamp, with newlines in message replaced.
+    pub fn text(&self) -> String {
+        let message = self.message.trim_end().replace('\n', " ");
+        let device = self
+            .device
+            .as_ref()
+            .map(|d| format!("{d}: "))
+            .unwrap_or_default();
+        format!("{:5} {device}{message}", self.level.as_str())
+    }
+
+    /// Returns true if entry contains `term`, ignoring ASCII case.
+    pub fn matches(&self, term: &str) -> bool {
+        !term.is_empty()
+            && self
+                .text()
+                .to_ascii_lowercase()
+                .contains(&term.to_ascii_lowercase())
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", local_rfc3339(self.time), self.text())
+    }
+}
+
+/// Formats time as RFC 3339 timestamp in local time zone, with its offset.
+/// Falls back to UTC if local time can't be determined.
+fn local_rfc3339(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let Ok(secs) = libc::time_t::try_from(secs) else {
+        return humantime::format_rfc3339_seconds(time).to_string();
+    };
+    // SAFETY: tm is plain data, all zeroes is valid value
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    // SAFETY: both pointers are valid for duration of call
+    if unsafe { libc::localtime_r(&raw const secs, &raw mut tm) }.is_null() {
+        return humantime::format_rfc3339_seconds(time).to_string();
+    }
+    let offset = tm.tm_gmtoff / 60;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}{:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec,
+        if offset < 0 { '-' } else { '+' },
+        offset.abs() / 60,
+        offset.abs() % 60
+    )
+}
+
+/// Bounded in-memory log, optionally mirrored to rotated file.
+pub struct Log {
+    entries: VecDeque<Entry>,
+    file: Option<(File, PathBuf)>,
+    next_id: u64,
+}
+
+impl Log {
+    pub fn new() -> Self {
+        Self {
+            entries: VecDeque::with_capacity(CAPACITY),
+            file: None,
+            next_id: 0,
+        }
+    }
+
+    /// Starts appending entries to file at `path`.
+    pub fn open(&mut self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        self.file = Some((file, path.to_path_buf()));
+        Ok(())
+    }
+
+    pub fn push(&mut self, mut entry: Entry) {
+        entry.id = self.next_id;
+        self.next_id += 1;
+        if let Some((file, path)) = &mut self.file {
+            // there is no better place to report failure to write log
+            _ = writeln!(file, "{entry}");
+            if file.metadata().is_ok_and(|m| m.len() > MAX_FILE_SIZE) {
+                if let Ok(f) = rotate(path) {
+                    *file = f;
+                }
+            }
+        }
+        if self.entries.len() == CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Entry> {
+        self.entries.iter()
+    }
+
+    /// Clears in-memory entries, log file is kept.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Shifts `path.N` to `path.N+1`, `path` to `path.1` and returns new file at
+/// `path`.
+fn rotate(path: &Path) -> Result<File> {
+    let rotated = |n: usize| {
+        let mut p = path.as_os_str().to_owned();
+        p.push(format!(".{n}"));
+        PathBuf::from(p)
+    };
+    for n in (1..ROTATED_FILES).rev() {
+        if rotated(n).exists() {
+            fs::rename(rotated(n), rotated(n + 1))?;
+        }
+    }
+    fs::rename(path, rotated(1))?;
+    Ok(OpenOptions::new().create(true).append(true).open(path)?)
+}
@@ -1,48 +1,79 @@
 mod api;
 mod app;
 mod cli;
+mod color;
+mod command;
+mod ctl;
+mod file;
+mod hass;
+mod history;
+mod input;
+mod keys;
+mod logs;
+mod merge;
+mod metrics;
+mod table;
+mod theme;
 mod ui;
 
 use anyhow::{Context, Result};
-use app::CurrentlySetting;
+use api::Selection;
 use cli::Subcmd;
 use crossterm::{
-    event::{Event, KeyCode, KeyEventKind},
+    event::{
+        DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
+    },
     terminal::{EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::{Backend, CrosstermBackend},
     Terminal,
 };
-use std::io;
+use std::{io, time::Duration};
 
 use crate::app::{App, CurrentWidget, CurrentlyAdding};
+use crate::keys::{Action, Keymap};
 
 fn main() -> Result<()> {
     let args = cli::parse();
 
-    let mut cfg = app::load_devices(args.config.clone())
+    let mut cfg = app::load_config(args.config.clone())
         .with_context(|| format!("failed to read config: {}", args.config.to_string_lossy()))?;
 
-    match &args.cmd {
-        Some(cmd) => match &cmd {
+    if let Some(cmd) = &args.cmd {
+        match &cmd {
             Subcmd::Cli(c) => {
-                if let Some(msg) = c.run(&mut cfg)? {
+                let forwarded = if c.is_forwardable() {
+                    ctl::forward(&args.socket, c)
+                } else {
+                    None
+                };
+                if let Some(msg) = forwarded.unwrap_or_else(|| c.run(&mut cfg.devices))? {
                     print!("{msg}");
                 }
             }
-        },
-        None => {
-            initialize_panic_handler();
-            setup_terminal()?;
-            let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+            Subcmd::Exporter(e) => {
+                metrics::Exporter::new(cfg.devices).serve(&e.listen)?;
+            }
+        }
+    } else {
+        let keymap = Keymap::new(&cfg.keys)
+            .with_context(|| format!("failed to read config: {}", args.config.to_string_lossy()))?;
 
-            let mut app = App::new(cfg, args.config);
-            let res = run_tui(&mut terminal, &mut app);
+        initialize_panic_handler();
+        setup_terminal()?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
 
-            restore_terminal()?;
-            return res;
-        }
+        let mut app = App::new(cfg, args.config, keymap);
+        app.open_log(&args.log_file);
+        app.open_history(&args.history_file);
+        app.lock_config();
+        app.listen(&args.socket);
+        let res = run_tui(&mut terminal, &mut app);
+
+        restore_terminal()?;
+        return res;
     }
 
     Ok(())
@@ -50,12 +81,22 @@ fn main() -> Result<()> {
 
 fn setup_terminal() -> Result<()> {
     crossterm::terminal::enable_raw_mode()?;
-    crossterm::execute!(io::stdout(), EnterAlternateScreen)?;
+    crossterm::execute!(
+        io::stdout(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
     Ok(())
 }
 
 fn restore_terminal() -> Result<()> {
-    crossterm::execute!(io::stdout(), LeaveAlternateScreen,)?;
+    crossterm::execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste
+    )?;
     crossterm::terminal::disable_raw_mode()?;
     Ok(())
 }
@@ -69,98 +110,91 @@ fn initialize_panic_handler() {
     }));
 }
 
+/// How long to wait for terminal events before checking other event sources.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 fn run_tui<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
     app.refresh_devices();
 
     loop {
         terminal.draw(|f| ui::ui(f, app))?;
 
-        if let Event::Key(key) = crossterm::event::read()? {
+        app.handle_control();
+        app.update_preview();
+        app.update_nudge();
+        app.update_config();
+        if !crossterm::event::poll(POLL_INTERVAL)? {
+            continue;
+        }
+        let event = crossterm::event::read()?;
+        if let Event::Mouse(mouse) = event {
+            handle_mouse(app, mouse);
+        }
+        if let Event::Paste(text) = &event {
+            app.paste(text);
+        }
+        if let Event::Key(key) = event {
             if key.kind == KeyEventKind::Release {
                 continue;
             }
+            let action = app.keymap.action(app.keys(), &key);
+            if app.show_help {
+                handle_help_key(app, action);
+                continue;
+            }
             match app.current_widget {
-                CurrentWidget::Devices => match key.code {
-                    KeyCode::Esc | KeyCode::Char('q') => return app.write_config(),
-                    KeyCode::Enter => app.toggle_current(),
-                    KeyCode::Tab => app.current_widget = CurrentWidget::Logs,
-                    KeyCode::Up | KeyCode::Char('k') => app.prev_device(),
-                    KeyCode::Down | KeyCode::Char('j') => app.next_device(),
-                    KeyCode::Char('a') => {
-                        app.current_widget = CurrentWidget::AddDevice;
-                        app.currently_adding = Some(CurrentlyAdding::IP);
+                CurrentWidget::Devices => {
+                    if handle_devices_key(app, action) {
+                        return app.quit();
                     }
-                    KeyCode::Char('A') => app.discover(),
-                    KeyCode::Char('c') => app.open_settings(),
-                    KeyCode::Char('d') => app.remove_device(),
-                    KeyCode::Char('e') => app.toggle_selected(),
-                    KeyCode::Char('r') => app.refresh_devices(),
-                    KeyCode::Char(' ') => app.select_device(),
-                    _ => {}
-                },
-                CurrentWidget::Logs => match key.code {
-                    KeyCode::Esc | KeyCode::Char('q') => return app.write_config(),
-                    KeyCode::Backspace => app.logs.clear(),
-                    KeyCode::Tab => app.current_widget = CurrentWidget::Devices,
-                    KeyCode::Left | KeyCode::Char('h') => app.scroll_logs_left(),
-                    KeyCode::Right | KeyCode::Char('l') => app.scroll_logs_right(),
-                    _ => {}
-                },
-                CurrentWidget::AddDevice => match key.code {
-                    KeyCode::Esc => {
+                }
+                CurrentWidget::DeviceSearch => match (action, key.code) {
+                    (Some(Action::Cancel), _) => {
+                        app.clear_device_filter();
                         app.current_widget = CurrentWidget::Devices;
-                        app.currently_adding = None;
                     }
-                    KeyCode::Enter => app.add_device(),
-                    KeyCode::Backspace => {
-                        if let Some(editing) = &app.currently_adding {
-                            match editing {
-                                CurrentlyAdding::IP => app.ip_input.pop(),
-                                CurrentlyAdding::Name => app.name_input.pop(),
-                            };
-                        }
+                    (Some(Action::Apply), _) => app.current_widget = CurrentWidget::Devices,
+                    (_, KeyCode::Backspace) => {
+                        app.device_filter.pop();
+                        app.filter_devices();
                     }
-                    KeyCode::Tab | KeyCode::Up | KeyCode::Down => app.toggle_adding_field(),
-                    KeyCode::Char(c) => {
-                        if let Some(editing) = &app.currently_adding {
-                            match editing {
-                                CurrentlyAdding::IP => app.ip_input.push(c),
-                                CurrentlyAdding::Name => app.name_input.push(c),
-                            }
-                        }
+                    (_, KeyCode::Char(c)) => {
+                        app.device_filter.push(c);
+                        app.filter_devices();
                     }
                     _ => {}
                 },
-                CurrentWidget::DeviceSettings => match key.code {
-                    KeyCode::Esc | KeyCode::Char('q') => {
-                        app.current_widget = CurrentWidget::Devices;
-                        app.currently_setting = None;
+                CurrentWidget::Logs | CurrentWidget::LogSearch => {
+                    if handle_logs_key(app, action, key.code) {
+                        return app.quit();
                     }
-                    KeyCode::Enter => app.set_color_and_brightness(),
-                    KeyCode::Backspace => {
-                        if let Some(setting) = &app.currently_setting {
-                            match setting {
-                                CurrentlySetting::Color => {
-                                    if app.color_input.len() > 1 {
-                                        app.color_input.pop();
-                                    }
-                                }
-                                CurrentlySetting::Brightness => _ = app.brightness_input.pop(),
-                            };
-                        }
+                }
+                CurrentWidget::AddDevice => match (action, key.code) {
+                    (Some(Action::Cancel), _) => app.close_popup(),
+                    (Some(Action::Apply), _) if app.editing.is_some() => app.edit_device(),
+                    (Some(Action::Apply), _) => app.add_device(),
+                    (Some(Action::NextField), _) => app.toggle_adding_field(),
+                    _ => app.edit_input(&key),
+                },
+                CurrentWidget::DeviceSettings => handle_settings_key(app, action, &key),
+                CurrentWidget::ConfirmRemove => match action {
+                    Some(Action::Apply) => app.confirm_removal(),
+                    Some(Action::Cancel) => app.current_widget = CurrentWidget::Devices,
+                    _ => {}
+                },
+                CurrentWidget::Command => match (action, key.code) {
+                    (Some(Action::Cancel), _) => app.close_command(),
+                    (Some(Action::Apply), _) => app.run_command(),
+                    (Some(Action::Complete), _) => app.complete_command(),
+                    (Some(Action::Up), _) => app.browse_commands(true),
+                    (Some(Action::Down), _) => app.browse_commands(false),
+                    (_, KeyCode::Backspace) => {
+                        app.command_input.pop();
+                        app.command_hint.clear();
                     }
-                    KeyCode::Tab | KeyCode::Up | KeyCode::Down => app.toggle_settings_field(),
-                    KeyCode::Char(c) => {
-                        if let Some(setting) = &app.currently_setting {
-                            match setting {
-                                CurrentlySetting::Color => {
-                                    if app.color_input.len() < 7 {
-                                        app.color_input.push(c);
-                                    }
-                                }
-                                CurrentlySetting::Brightness => app.brightness_input.push(c),
-                            }
-                        }
+                    (_, KeyCode::Char(c)) => {
+                        app.command_input.push(c);
+                        app.command_hint.clear();
                     }
                     _ => {}
                 },
@@ -168,3 +202,133 @@ fn run_tui<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()>
         }
     }
 }
+
+/// Handles key pressed in device list, returns true if app should quit.
+fn handle_devices_key(app: &mut App, action: Option<Action>) -> bool {
+    match action {
+        Some(Action::Quit) => return true,
+        Some(Action::ToggleCurrent) => app.toggle_current(),
+        Some(Action::SwitchPane) => app.current_widget = CurrentWidget::Logs,
+        Some(Action::Up) => app.prev_device(),
+        Some(Action::Down) => app.next_device(),
+        Some(Action::MoveUp) => app.move_device(-1),
+        Some(Action::MoveDown) => app.move_device(1),
+        Some(Action::Add) => {
+            app.current_widget = CurrentWidget::AddDevice;
+            app.currently_adding = Some(CurrentlyAdding::IP);
+        }
+        Some(Action::Edit) => app.open_edit(),
+        Some(Action::Discover) => app.discover(),
+        Some(Action::Settings) => app.open_settings(),
+        Some(Action::Remove) => app.remove_device(),
+        Some(Action::Restore) => app.restore_device(),
+        Some(Action::ToggleSelected) => app.toggle_selected(),
+        Some(Action::Refresh) => app.refresh_devices(),
+        Some(Action::Undo) => app.undo(),
+        Some(Action::Redo) => app.redo(),
+        Some(Action::Select) => app.select_device(),
+        Some(Action::SelectAll) => app.select_devices(Selection::All),
+        Some(Action::SelectNone) => app.select_devices(Selection::None),
+        Some(Action::InvertSelection) => app.select_devices(Selection::Invert),
+        Some(Action::SelectOn) => app.select_devices(Selection::On),
+        Some(Action::SelectOff) => app.select_devices(Selection::Off),
+        Some(Action::SelectFiltered) => app.select_devices(Selection::Filtered),
+        Some(Action::Search) => app.current_widget = CurrentWidget::DeviceSearch,
+        Some(Action::Sort) => app.table.cycle_sort(),
+        Some(Action::ReverseSort) => app.table.toggle_reverse(),
+        Some(Action::Details) => app.show_details = !app.show_details,
+        Some(Action::Help) => app.open_help(),
+        Some(Action::Command) => app.open_command(),
+        Some(Action::BrightnessUp) => app.nudge_brightness(1.0),
+        Some(Action::BrightnessDown) => app.nudge_brightness(-1.0),
+        Some(Action::HueLeft) => app.nudge_hue(-1.0),
+        Some(Action::HueRight) => app.nudge_hue(1.0),
+        Some(Action::BrightnessLevel(level)) => app.set_brightness_level(f32::from(level) / 10.0),
+        _ => {}
+    }
+    false
+}
+
+/// Handles key pressed while help overlay is shown.
+fn handle_help_key(app: &mut App, action: Option<Action>) {
+    let page = app.help_height.max(1);
+    match action {
+        Some(Action::Cancel) => app.show_help = false,
+        Some(Action::Up) => app.help_offset = app.help_offset.saturating_sub(1),
+        Some(Action::Down) => app.help_offset = app.help_offset.saturating_add(1),
+        Some(Action::PageUp) => app.help_offset = app.help_offset.saturating_sub(page),
+        Some(Action::PageDown) => app.help_offset = app.help_offset.saturating_add(page),
+        Some(Action::Top) => app.help_offset = 0,
+        Some(Action::Bottom) => app.help_offset = u16::MAX,
+        _ => {}
+    }
+}
+
+fn handle_mouse(app: &mut App, mouse: MouseEvent) {
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) => app.click(mouse.column, mouse.row),
+        MouseEventKind::ScrollUp => app.scroll(mouse.column, mouse.row, true),
+        MouseEventKind::ScrollDown => app.scroll(mouse.column, mouse.row, false),
+        _ => {}
+    }
+}
+
+/// Handles key pressed in device settings popup.
+fn handle_settings_key(app: &mut App, action: Option<Action>, key: &KeyEvent) {
+    match action {
+        Some(Action::Cancel) => app.close_popup(),
+        Some(Action::Apply) => app.set_color_and_brightness(),
+        Some(Action::NextField) => app.toggle_settings_field(),
+        Some(Action::Preview) => app.toggle_preview(),
+        Some(Action::Left) => app.move_picker(-1.0, 0.0),
+        Some(Action::Right) => app.move_picker(1.0, 0.0),
+        Some(Action::Up) => app.move_picker(0.0, 1.0),
+        Some(Action::Down) => app.move_picker(0.0, -1.0),
+        _ => app.edit_input(key),
+    }
+}
+
+/// Handles key pressed in logs view, returns true if app should quit.
+fn handle_logs_key(app: &mut App, action: Option<Action>, code: KeyCode) -> bool {
+    match app.current_widget {
+        CurrentWidget::Logs => match action {
+            Some(Action::Quit) => return true,
+            Some(Action::ClearLogs) => app.logs.clear(),
+            Some(Action::SwitchPane) => app.current_widget = CurrentWidget::Devices,
+            Some(Action::Left) => app.scroll_logs_left(),
+            Some(Action::Right) => app.scroll_logs_right(),
+            Some(Action::Up) => app.scroll_logs_up(1),
+            Some(Action::Down) => app.scroll_logs_down(1),
+            Some(Action::PageUp) => app.scroll_logs_page_up(),
+            Some(Action::PageDown) => app.scroll_logs_page_down(),
+            Some(Action::Top) => app.scroll_logs_top(),
+            Some(Action::Bottom) => app.scroll_logs_bottom(),
+            Some(Action::Search) => {
+                app.log_search.clear();
+                app.current_widget = CurrentWidget::LogSearch;
+            }
+            Some(Action::NextMatch) => app.find_log(true),
+            Some(Action::PrevMatch) => app.find_log(false),
+            Some(Action::CycleLevel) => app.cycle_log_level(),
+            Some(Action::FilterDevice) => app.toggle_log_device(),
+            Some(Action::Help) => app.open_help(),
+            Some(Action::Command) => app.open_command(),
+            _ => {}
+        },
+        CurrentWidget::LogSearch => match (action, code) {
+            (Some(Action::Cancel), _) => {
+                app.log_search.clear();
+                app.current_widget = CurrentWidget::Logs;
+            }
+            (Some(Action::Apply), _) => {
+                app.current_widget = CurrentWidget::Logs;
+                app.find_log(true);
+            }
+            (_, KeyCode::Backspace) => _ = app.log_search.pop(),
+            (_, KeyCode::Char(c)) => app.log_search.push(c),
+            _ => {}
+        },
+        _ => {}
+    }
+    false
+}
@@ -0,0 +1,252 @@
+use crate::api::{Device, Devices};
+use crate::logs::{Entry, Level};
+
+/// Result of merging one value changed on disk.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Merge {
+    /// Value on disk didn't change, or changed to local value.
+    Unchanged,
+    /// Value on disk was taken.
+    Taken,
+    /// Value changed both on disk and here, local one was kept.
+    Conflict,
+}
+
+/// Takes value changed on disk since `base` was read, unless local value
+/// changed too.
+pub fn merge_field<T: PartialEq>(base: Option<&T>, theirs: T, ours: &mut T) -> Merge {
+    if theirs == *ours || base == Some(&theirs) {
+        Merge::Unchanged
+    } else if base == Some(ours) {
+        *ours = theirs;
+        Merge::Taken
+    } else {
+        Merge::Conflict
+    }
+}
+
+/// Merges devices changed on disk since `base` was read into `ours`. Name,
+/// selection and groups are merged field by field, state of bulbs is left
+/// to refreshes. Returns log entries describing what was merged.
+pub fn merge_devices(base: &Devices, theirs: Devices, ours: &mut Devices) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let entry = |level, ip: &str, message: &str| {
+        Entry::new(level, Some(ip.to_string()), message.to_string())
+    };
+
+    for old in &base.bulbs {
+        if theirs.bulbs.iter().any(|d| d.ip == old.ip) {
+            continue;
+        }
+        match ours.bulbs.iter().position(|d| d.ip == old.ip) {
+            Some(i) if same_settings(&ours.bulbs[i], old) => {
+                ours.bulbs.remove(i);
+                entries.push(entry(Level::Info, &old.ip, "Device removed in config file"));
+            }
+            Some(_) => entries.push(entry(
+                Level::Warn,
+                &old.ip,
+                "Device removed in config file but changed here, keeping it",
+            )),
+            None => (),
+        }
+    }
+
+    for new in theirs.bulbs {
+        let old = base.bulbs.iter().find(|d| d.ip == new.ip);
+        match (ours.bulbs.iter_mut().find(|d| d.ip == new.ip), old) {
+            (Some(dev), old) => {
+                let ip = new.ip.clone();
+                let results = [
+                    (
+                        "name",
+                        merge_field(old.map(|d| &d.name), new.name, &mut dev.name),
+                    ),
+                    (
+                        "selection",
+                        merge_field(old.map(|d| &d.selected), new.selected, &mut dev.selected),
+                    ),
+                    (
+                        "groups",
+                        merge_field(old.map(|d| &d.groups), new.groups, &mut dev.groups),
+                    ),
+                ];
+                let fields = |merge: Merge| {
+                    results
+                        .iter()
+                        .filter(|(_, m)| *m == merge)
+                        .map(|(field, _)| *field)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                let taken = fields(Merge::Taken);
+                if !taken.is_empty() {
+                    let message = format!("Device updated from config file: {taken}");
+                    entries.push(entry(Level::Info, &ip, &message));
+                }
+                let conflicts = fields(Merge::Conflict);
+                if !conflicts.is_empty() {
+                    let message =
+                        format!("Changed in config file and here: {conflicts}, keeping local");
+                    entries.push(entry(Level::Warn, &ip, &message));
+                }
+            }
+            (None, Some(old)) if !same_settings(&new, old) => entries.push(entry(
+                Level::Warn,
+                &new.ip,
+                "Device changed in config file but removed here, keeping it removed",
+            )),
+            (None, Some(_)) => (),
+            (None, None) => {
+                entries.push(entry(Level::Info, &new.ip, "Device added in config file"));
+                ours.bulbs.push(new);
+            }
+        }
+    }
+    entries
+}
+
+/// Returns true if settings saved in config file are equal.
+fn same_settings(a: &Device, b: &Device) -> bool {
+    a.name == b.name && a.selected == b.selected && a.groups == b.groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns devices with given addresses and names.
+    fn devices(list: &[(&str, &str)]) -> Devices {
+        let mut devices: Devices = toml::from_str("").unwrap();
+        for (ip, name) in list {
+            devices.bulbs.push(Device {
+                ip: (*ip).to_string(),
+                name: (*name).to_string(),
+                ..Device::default()
+            });
+        }
+        devices
+    }
+
+    fn names(devices: &Devices) -> Vec<(&str, &str)> {
+        devices
+            .bulbs
+            .iter()
+            .map(|d| (d.ip.as_str(), d.name.as_str()))
+            .collect()
+    }
+
+    fn messages(entries: &[Entry]) -> Vec<(Level, &str)> {
+        entries
+            .iter()
+            .map(|e| (e.level, e.message.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn merges_fields() {
+        let mut ours = 1;
+        assert_eq!(merge_field(Some(&1), 1, &mut ours), Merge::Unchanged);
+        assert_eq!(merge_field(Some(&1), 2, &mut ours), Merge::Taken);
+        assert_eq!(ours, 2);
+
+        let mut ours = 3;
+        assert_eq!(merge_field(Some(&1), 2, &mut ours), Merge::Conflict);
+        assert_eq!(ours, 3);
+        assert_eq!(merge_field(Some(&1), 3, &mut ours), Merge::Unchanged);
+        assert_eq!(merge_field(None, 2, &mut ours), Merge::Conflict);
+    }
+
+    #[test]
+    fn takes_device_changed_on_disk() {
+        let base = devices(&[("a", "desk"), ("b", "hall")]);
+        let theirs = devices(&[("a", "office"), ("b", "hall")]);
+        let mut ours = devices(&[("a", "desk"), ("b", "hall")]);
+
+        let entries = merge_devices(&base, theirs, &mut ours);
+        assert_eq!(names(&ours), [("a", "office"), ("b", "hall")]);
+        assert_eq!(
+            messages(&entries),
+            [(Level::Info, "Device updated from config file: name")]
+        );
+    }
+
+    #[test]
+    fn keeps_device_changed_on_both_sides() {
+        let base = devices(&[("a", "desk")]);
+        let theirs = devices(&[("a", "office")]);
+        let mut ours = devices(&[("a", "study")]);
+
+        let entries = merge_devices(&base, theirs, &mut ours);
+        assert_eq!(names(&ours), [("a", "study")]);
+        assert_eq!(
+            messages(&entries),
+            [(
+                Level::Warn,
+                "Changed in config file and here: name, keeping local"
+            )]
+        );
+    }
+
+    #[test]
+    fn removes_device_removed_on_disk() {
+        let base = devices(&[("a", "desk"), ("b", "hall")]);
+        let theirs = devices(&[("b", "hall")]);
+        let mut ours = devices(&[("a", "desk"), ("b", "hall")]);
+
+        let entries = merge_devices(&base, theirs, &mut ours);
+        assert_eq!(names(&ours), [("b", "hall")]);
+        assert_eq!(
+            messages(&entries),
+            [(Level::Info, "Device removed in config file")]
+        );
+    }
+
+    #[test]
+    fn keeps_device_removed_on_disk_but_changed_here() {
+        let base = devices(&[("a", "desk")]);
+        let theirs = devices(&[]);
+        let mut ours = devices(&[("a", "study")]);
+
+        let entries = merge_devices(&base, theirs, &mut ours);
+        assert_eq!(names(&ours), [("a", "study")]);
+        assert_eq!(
+            messages(&entries),
+            [(
+                Level::Warn,
+                "Device removed in config file but changed here, keeping it"
+            )]
+        );
+    }
+
+    #[test]
+    fn keeps_device_removed_here_but_changed_on_disk() {
+        let base = devices(&[("a", "desk")]);
+        let theirs = devices(&[("a", "office")]);
+        let mut ours = devices(&[]);
+
+        let entries = merge_devices(&base, theirs, &mut ours);
+        assert!(ours.bulbs.is_empty());
+        assert_eq!(
+            messages(&entries),
+            [(
+                Level::Warn,
+                "Device changed in config file but removed here, keeping it removed"
+            )]
+        );
+    }
+
+    #[test]
+    fn adds_device_added_on_disk() {
+        let base = devices(&[("a", "desk")]);
+        let theirs = devices(&[("a", "desk"), ("b", "hall")]);
+        let mut ours = devices(&[("a", "desk")]);
+
+        let entries = merge_devices(&base, theirs, &mut ours);
+        assert_eq!(names(&ours), [("a", "desk"), ("b", "hall")]);
+        assert_eq!(
+            messages(&entries),
+            [(Level::Info, "Device added in config file")]
+        );
+    }
+}
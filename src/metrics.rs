@@ -0,0 +1,225 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    time::Duration,
+};
+
+use anyhow::Result;
+
+use crate::api::{Device, Devices, Selection};
+
+/// Upper bounds of request latency histogram buckets, in seconds.
+const BUCKETS: [f64; 9] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, d: Duration) {
+        let secs = d.as_secs_f64();
+        for (i, le) in BUCKETS.iter().enumerate() {
+            if secs <= *le {
+                self.buckets[i] += 1;
+            }
+        }
+        self.sum += secs;
+        self.count += 1;
+    }
+}
+
+/// Prometheus exporter, refreshes status of all configured devices on every
+/// scrape, all of them at once so that scrape takes as long as slowest device.
+pub struct Exporter {
+    devices: Devices,
+    latency: BTreeMap<String, Histogram>,
+}
+
+impl Exporter {
+    pub fn new(mut devices: Devices) -> Self {
+        // selection only picks devices commands act on, health of every
+        // device is reported
+        Selection::All.apply(&mut devices);
+        Self {
+            devices,
+            latency: BTreeMap::new(),
+        }
+    }
+
+    pub fn serve(&mut self, addr: &str) -> Result<()> {
+        let listener = TcpListener::bind(addr)?;
+        for stream in listener.incoming() {
+            // a broken scrape shouldn't stop the exporter
+            _ = stream.map_err(Into::into).and_then(|s| self.handle(s));
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, mut stream: TcpStream) -> Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let mut reader = BufReader::new(&stream);
+        let mut request = String::new();
+        reader.read_line(&mut request)?;
+        // skip headers, nothing in them is needed
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 2 {
+            line.clear();
+        }
+
+        let (status, body) = match request.split_whitespace().nth(1) {
+            Some("/metrics") => ("200 OK", self.scrape()),
+            Some("/") => (
+                "200 OK",
+                "bulbs-tui exporter, metrics are at /metrics\n".to_string(),
+            ),
+            _ => ("404 Not Found", "not found\n".to_string()),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {status}\r\n\
+             Content-Type: text/plain; version=0.0.4\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\r\n{body}",
+            body.len()
+        )?;
+        Ok(())
+    }
+
+    fn scrape(&mut self) -> String {
+        // errors are reported through metrics
+        _ = self.devices.refresh();
+        for dev in &self.devices.bulbs {
+            if let Some(d) = dev.stats.latency {
+                self.latency.entry(dev.ip.clone()).or_default().observe(d);
+            }
+        }
+        self.render()
+    }
+
+    fn render(&self) -> String {
+        let devices = &self.devices.bulbs;
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "bulbs_up",
+            "gauge",
+            "Whether device responded to last request.",
+        );
+        for dev in devices {
+            _ = writeln!(
+                out,
+                "bulbs_up{{{}}} {}",
+                labels(dev),
+                u8::from(dev.stats.up)
+            );
+        }
+
+        header(
+            &mut out,
+            "bulbs_request_duration_seconds",
+            "histogram",
+            "Latency of requests to device.",
+        );
+        for dev in devices {
+            let Some(h) = self.latency.get(&dev.ip) else {
+                continue;
+            };
+            let l = labels(dev);
+            for (le, count) in BUCKETS.iter().zip(h.buckets) {
+                _ = writeln!(
+                    out,
+                    "bulbs_request_duration_seconds_bucket{{{l},le=\"{le}\"}} {count}"
+                );
+            }
+            _ = writeln!(
+                out,
+                "bulbs_request_duration_seconds_bucket{{{l},le=\"+Inf\"}} {}",
+                h.count
+            );
+            _ = writeln!(out, "bulbs_request_duration_seconds_sum{{{l}}} {}", h.sum);
+            _ = writeln!(
+                out,
+                "bulbs_request_duration_seconds_count{{{l}}} {}",
+                h.count
+            );
+        }
+
+        header(
+            &mut out,
+            "bulbs_request_errors_total",
+            "counter",
+            "Number of failed requests to device by kind of error.",
+        );
+        for dev in devices {
+            for (kind, count) in &dev.stats.errors {
+                _ = writeln!(
+                    out,
+                    "bulbs_request_errors_total{{{},kind=\"{}\"}} {count}",
+                    labels(dev),
+                    kind.as_str()
+                );
+            }
+        }
+
+        header(&mut out, "bulbs_power", "gauge", "Whether LED is on.");
+        for dev in devices {
+            _ = writeln!(out, "bulbs_power{{{}}} {}", labels(dev), dev.bulb.enabled);
+        }
+
+        header(
+            &mut out,
+            "bulbs_brightness",
+            "gauge",
+            "LED brightness, from 0 to 1.",
+        );
+        for dev in devices {
+            _ = writeln!(
+                out,
+                "bulbs_brightness{{{}}} {}",
+                labels(dev),
+                dev.bulb.brightness
+            );
+        }
+
+        header(
+            &mut out,
+            "bulbs_color",
+            "gauge",
+            "LED color channel value, from 0 to 255.",
+        );
+        for dev in devices {
+            let (r, g, b) = dev.bulb.rgb();
+            for (channel, v) in [("red", r), ("green", g), ("blue", b)] {
+                _ = writeln!(
+                    out,
+                    "bulbs_color{{{},channel=\"{channel}\"}} {v}",
+                    labels(dev)
+                );
+            }
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    _ = writeln!(out, "# HELP {name} {help}");
+    _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn labels(dev: &Device) -> String {
+    format!("ip=\"{}\",name=\"{}\"", escape(&dev.ip), escape(&dev.name))
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
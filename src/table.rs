@@ -0,0 +1,187 @@
+use std::{cmp::Ordering, time::Duration};
+
+use ratatui::{
+    layout::Constraint,
+    style::{Color, Style},
+    text::{Line, Span},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{api::Device, theme::Theme};
+
+/// Width of brightness bar, without percentage.
+const BAR_WIDTH: usize = 10;
+
+/// Column of device list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    Name,
+    Address,
+    Power,
+    Brightness,
+    Color,
+    Latency,
+    LastSeen,
+}
+
+/// `[table]` section of config file.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TableConfig {
+    #[serde(default = "default_columns")]
+    pub columns: Vec<Column>,
+
+    /// Column devices are sorted by, config order if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<Column>,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reverse: bool,
+}
+
+fn default_columns() -> Vec<Column> {
+    vec![
+        Column::Address,
+        Column::Name,
+        Column::Power,
+        Column::Brightness,
+        Column::Color,
+    ]
+}
+
+impl Default for TableConfig {
+    fn default() -> Self {
+        Self {
+            columns: default_columns(),
+            sort: None,
+            reverse: false,
+        }
+    }
+}
+
+impl TableConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Sorts by next column, after last one goes back to config order.
+    pub fn cycle_sort(&mut self) {
+        let next = match self.sort {
+            None => 0,
+            Some(c) => self
+                .columns
+                .iter()
+                .position(|&x| x == c)
+                .map_or(0, |i| i + 1),
+        };
+        self.sort = self.columns.get(next).copied();
+    }
+
+    pub const fn toggle_reverse(&mut self) {
+        self.reverse = !self.reverse;
+    }
+
+    /// Compares devices by sort column.
+    pub fn compare(&self, a: &Device, b: &Device) -> Ordering {
+        let ord = self.sort.map_or(Ordering::Equal, |c| c.compare(a, b));
+        if self.reverse {
+            ord.reverse()
+        } else {
+            ord
+        }
+    }
+}
+
+impl Column {
+    pub const fn title(self) -> &'static str {
+        match self {
+            Self::Name => "Name",
+            Self::Address => "Address",
+            Self::Power => "Power",
+            Self::Brightness => "Brightness",
+            Self::Color => "Color",
+            Self::Latency => "Latency",
+            Self::LastSeen => "Last seen",
+        }
+    }
+
+    /// Returns width constraint fitting column in `devices`.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn width<'a>(self, devices: impl Iterator<Item = &'a Device>, theme: &Theme) -> Constraint {
+        let content = match self {
+            Self::Name => devices.map(|d| d.name.chars().count()).max().unwrap_or(0),
+            Self::Address => devices.map(|d| d.ip.chars().count()).max().unwrap_or(0),
+            Self::Power => 3,
+            Self::Brightness => BAR_WIDTH + 5,
+            Self::Color if theme.bulb_colors => 11,
+            Self::Color | Self::Latency => 7,
+            Self::LastSeen => 12,
+        };
+        // sort marker may follow title
+        Constraint::Length(content.max(self.title().len() + 2) as u16)
+    }
+
+    pub fn cell<'a>(self, dev: &Device, theme: &Theme) -> Line<'a> {
+        match self {
+            Self::Name => Line::raw(dev.name.clone()),
+            Self::Address => Line::raw(dev.ip.clone()),
+            Self::Power => Line::raw(if dev.bulb.enabled == 1 { "ON" } else { "OFF" }),
+            Self::Brightness => Line::raw(brightness_bar(dev.bulb.brightness)),
+            Self::Color => {
+                let mut spans = vec![Span::raw(dev.bulb.color.clone())];
+                if theme.bulb_colors {
+                    let (r, g, b) = dev.bulb.rgb();
+                    spans.push(Span::raw(" "));
+                    spans.push(Span::styled("   ", Style::new().bg(Color::Rgb(r, g, b))));
+                }
+                Line::from(spans)
+            }
+            Self::Latency => Line::raw(
+                dev.stats
+                    .latency
+                    .map_or_else(|| "-".to_string(), |l| format!("{} ms", l.as_millis())),
+            ),
+            Self::LastSeen => Line::raw(last_seen(dev).unwrap_or_else(|| "-".to_string())),
+        }
+    }
+
+    fn compare(self, a: &Device, b: &Device) -> Ordering {
+        match self {
+            Self::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            Self::Address => a.ip.cmp(&b.ip),
+            Self::Power => a.bulb.enabled.cmp(&b.bulb.enabled),
+            Self::Brightness => a.bulb.brightness.total_cmp(&b.bulb.brightness),
+            Self::Color => a
+                .bulb
+                .color
+                .to_lowercase()
+                .cmp(&b.bulb.color.to_lowercase()),
+            Self::Latency => a.stats.latency.cmp(&b.stats.latency),
+            Self::LastSeen => a.stats.last_seen.cmp(&b.stats.last_seen),
+        }
+    }
+}
+
+/// Returns how long ago device last responded, in whole seconds.
+pub fn last_seen(dev: &Device) -> Option<String> {
+    let elapsed = dev.stats.last_seen?.elapsed().ok()?;
+    let secs = Duration::from_secs(elapsed.as_secs());
+    Some(format!("{} ago", humantime::format_duration(secs)))
+}
+
+/// Returns bar of brightness in range 0..1, followed by percentage.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn brightness_bar(brightness: f32) -> String {
+    let brightness = brightness.clamp(0.0, 1.0);
+    let filled = (brightness * BAR_WIDTH as f32).round() as usize;
+    format!(
+        "{}{} {:3.0}%",
+        "█".repeat(filled),
+        "░".repeat(BAR_WIDTH - filled),
+        brightness * 100.0
+    )
+}
@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+}
+
+/// Part of UI that color can be set for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Slot {
+    Border,
+    ActiveBorder,
+    HighlightFg,
+    HighlightBg,
+    On,
+    Off,
+    Text,
+    Key,
+    InputFg,
+    InputBg,
+    MatchFg,
+    MatchBg,
+    Error,
+    Warn,
+}
+
+/// `[theme]` section of config file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ThemeConfig {
+    #[serde(default)]
+    pub name: ThemeName,
+
+    /// Show bulb colors even if `NO_COLOR` is set.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bulb_colors: bool,
+
+    /// Colors overriding ones from bundled theme.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub colors: BTreeMap<Slot, Color>,
+}
+
+impl ThemeConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+pub struct Theme {
+    pub border: Style,
+    pub active_border: Style,
+    /// Style of row under cursor.
+    pub highlight: Style,
+    pub on: Style,
+    pub off: Style,
+    pub text: Style,
+    pub key: Style,
+    /// Style of currently edited input field.
+    pub input: Style,
+    /// Style of search matches.
+    pub matched: Style,
+    pub error: Style,
+    pub warn: Style,
+    /// Whether to paint bulb colors.
+    pub bulb_colors: bool,
+}
+
+impl Theme {
+    /// Returns theme from config, or monochrome one if `no_color` is set.
+    pub fn new(config: &ThemeConfig, no_color: bool) -> Self {
+        if no_color {
+            return Self::monochrome(config.bulb_colors);
+        }
+
+        let mut colors: BTreeMap<Slot, Color> = palette(config.name).iter().copied().collect();
+        colors.extend(&config.colors);
+        // slots without color keep color of whatever is underneath
+        let style = |fg: Slot, bg: Option<Slot>| Style {
+            fg: colors.get(&fg).copied(),
+            bg: bg.and_then(|bg| colors.get(&bg).copied()),
+            ..Style::new()
+        };
+        Self {
+            border: style(Slot::Border, None),
+            active_border: style(Slot::ActiveBorder, None),
+            highlight: style(Slot::HighlightFg, Some(Slot::HighlightBg)),
+            on: style(Slot::On, None).bold(),
+            off: style(Slot::Off, None).bold(),
+            text: style(Slot::Text, None),
+            key: style(Slot::Key, None).bold(),
+            input: style(Slot::InputFg, Some(Slot::InputBg)),
+            matched: style(Slot::MatchFg, Some(Slot::MatchBg)),
+            error: style(Slot::Error, None),
+            warn: style(Slot::Warn, None),
+            bulb_colors: true,
+        }
+    }
+
+    /// Theme that conveys state with text attributes only.
+    fn monochrome(bulb_colors: bool) -> Self {
+        Self {
+            border: Style::new(),
+            active_border: Style::new().bold(),
+            highlight: Style::new().add_modifier(Modifier::REVERSED),
+            on: Style::new().bold(),
+            off: Style::new().dim(),
+            text: Style::new(),
+            key: Style::new().bold(),
+            input: Style::new().add_modifier(Modifier::REVERSED),
+            matched: Style::new().add_modifier(Modifier::REVERSED | Modifier::UNDERLINED),
+            error: Style::new().bold(),
+            warn: Style::new(),
+            bulb_colors,
+        }
+    }
+}
+
+/// Returns true if user asked for no colors, see <https://no-color.org>.
+pub fn no_color() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty())
+}
+
+const fn palette(name: ThemeName) -> &'static [(Slot, Color)] {
+    match name {
+        ThemeName::Dark => &[
+            (Slot::ActiveBorder, Color::LightBlue),
+            (Slot::HighlightBg, Color::LightBlue),
+            (Slot::On, Color::Blue),
+            (Slot::Off, Color::DarkGray),
+            (Slot::Text, Color::White),
+            (Slot::Key, Color::Blue),
+            (Slot::InputFg, Color::Black),
+            (Slot::InputBg, Color::Blue),
+            (Slot::MatchFg, Color::Black),
+            (Slot::MatchBg, Color::Yellow),
+            (Slot::Error, Color::Red),
+            (Slot::Warn, Color::Yellow),
+        ],
+        ThemeName::Light => &[
+            (Slot::ActiveBorder, Color::Blue),
+            (Slot::HighlightFg, Color::Black),
+            (Slot::HighlightBg, Color::Rgb(0xbb, 0xd6, 0xf2)),
+            (Slot::On, Color::Blue),
+            (Slot::Off, Color::Gray),
+            (Slot::Text, Color::Black),
+            (Slot::Key, Color::Blue),
+            (Slot::InputFg, Color::White),
+            (Slot::InputBg, Color::Blue),
+            (Slot::MatchFg, Color::Black),
+            (Slot::MatchBg, Color::LightYellow),
+            (Slot::Error, Color::Red),
+            (Slot::Warn, Color::Rgb(0xa0, 0x60, 0x00)),
+        ],
+        ThemeName::HighContrast => &[
+            (Slot::Border, Color::White),
+            (Slot::ActiveBorder, Color::LightYellow),
+            (Slot::HighlightFg, Color::Black),
+            (Slot::HighlightBg, Color::White),
+            (Slot::On, Color::LightGreen),
+            (Slot::Off, Color::Gray),
+            (Slot::Text, Color::White),
+            (Slot::Key, Color::LightYellow),
+            (Slot::InputFg, Color::Black),
+            (Slot::InputBg, Color::LightYellow),
+            (Slot::MatchFg, Color::Black),
+            (Slot::MatchBg, Color::LightCyan),
+            (Slot::Error, Color::LightRed),
+            (Slot::Warn, Color::LightYellow),
+        ],
+    }
+}
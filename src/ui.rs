@@ -1,120 +1,438 @@
+use std::{fmt::Write, time::Duration};
+
 use ratatui::{
     prelude::*,
-    widgets::{block::Block, Borders, Clear, List, ListItem, Paragraph},
+    widgets::{
+        block::Block, Borders, Cell, Clear, Paragraph, Row, Scrollbar, ScrollbarOrientation,
+        ScrollbarState, Sparkline, Table, Wrap,
+    },
     Frame,
 };
 
-use crate::app::{App, CurrentWidget, CurrentlyAdding, CurrentlySetting};
+use crate::{
+    app::{App, CurrentWidget, CurrentlyAdding, CurrentlySetting, Target},
+    color::Hsv,
+    input::Input,
+    keys,
+    logs::{Entry, Level},
+    table::{self, Column},
+    theme::Theme,
+};
 
-pub fn ui(f: &mut Frame, app: &App) {
-    #[allow(clippy::cast_possible_truncation)]
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(1),
-            Constraint::Min(app.devices.bulbs.len() as u16 + 2),
-            Constraint::Length(app.logs.len() as u16 + 2),
-            Constraint::Length(1),
-        ])
-        .split(f.size());
+/// Height of log view, including borders.
+const LOGS_HEIGHT: u16 = 10;
+/// Width of device detail panel, including borders.
+const DETAILS_WIDTH: u16 = 36;
+/// Most lines help bar wraps to.
+const HELP_MAX_HEIGHT: u16 = 3;
+/// Least height of device list: borders, header and two rows.
+const DEVICES_MIN_HEIGHT: u16 = 5;
+/// Rows of saturation/value grid of color picker.
+const PICKER_HEIGHT: u16 = 8;
+/// Height of device settings popup: color, picker and brightness fields.
+const SETTINGS_HEIGHT: u16 = 3 + PICKER_HEIGHT + 4 + 3;
 
-    let help: Line<'_>;
-    let mut log_block = Block::default().borders(Borders::ALL);
-    let mut devices_block = Block::default().borders(Borders::ALL);
+pub fn ui(f: &mut Frame, app: &mut App) {
+    let theme = &app.theme;
+    let mut log_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.border);
+    let mut devices_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(theme.border);
 
+    let help = help_bar(app);
+    // help bar wraps on narrow terminals, words may need extra line
+    #[allow(clippy::cast_possible_truncation)]
+    let help_height = match help.width().div_ceil(f.size().width.max(1).into()) {
+        0 | 1 => 1,
+        n => (n as u16 + 1).min(HELP_MAX_HEIGHT),
+    };
+
+    let [header_area, mut body, help_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Fill(1),
+        Constraint::Length(help_height),
+    ])
+    .areas(f.size());
     match &app.current_widget {
-        CurrentWidget::Devices => {
-            help = Line::from(vec![
-                " Add device: ".white(),
-                "a".blue().bold(),
-                " | Autodetect: ".white(),
-                "A".blue().bold(),
-                " | Change color: ".white(),
-                "c".blue().bold(),
-                " | Remove: ".white(),
-                "d".blue().bold(),
-                " | ON/OFF: ".white(),
-                "e".blue().bold(),
-                " | Refresh: ".white(),
-                "r".blue().bold(),
-                " | ON/OFF (one): ".white(),
-                "<enter>".blue().bold(),
-                " | Select: ".white(),
-                "<space>".blue().bold(),
-                " | Quit: ".white(),
-                "q".blue().bold(),
-            ]);
-            devices_block = devices_block.border_style(Style::new().light_blue());
-        }
-        CurrentWidget::Logs => {
-            log_block = log_block.border_style(Style::new().light_blue());
-            help = Line::from(vec![
-                " Clear: ".white(),
-                "<backspace>".blue().bold(),
-                " | Quit: ".white(),
-                "q".blue().bold(),
-            ]);
+        CurrentWidget::Devices | CurrentWidget::DeviceSearch | CurrentWidget::ConfirmRemove => {
+            devices_block = devices_block.border_style(theme.active_border);
         }
-        CurrentWidget::DeviceSettings | CurrentWidget::AddDevice => {
-            help = Line::from(vec![
-                " Apply: ".white(),
-                "<enter>".blue().bold(),
-                " | Cancel: ".white(),
-                "<esc>".blue().bold(),
-            ]);
+        CurrentWidget::Logs | CurrentWidget::LogSearch => {
+            log_block = log_block.border_style(theme.active_border);
         }
+        CurrentWidget::DeviceSettings | CurrentWidget::AddDevice | CurrentWidget::Command => (),
     }
 
-    let mut list_items = Vec::<ListItem>::new();
+    // detail panel spans height of both device list and logs
+    if app.show_details {
+        let [left, details] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Length(DETAILS_WIDTH)])
+                .areas(body);
+        body = left;
+        render_details(f, app, details);
+    }
+    // logs shrink before device list gets too small to scroll
+    let [devices_area, logs_area] = Layout::vertical([
+        Constraint::Min(DEVICES_MIN_HEIGHT),
+        Constraint::Length(LOGS_HEIGHT),
+    ])
+    .areas(body);
 
-    for (i, dev) in app.devices.bulbs.iter().enumerate() {
-        let mut style = Style::default().bold();
-        if dev.bulb.enabled == 1 {
-            style = style.blue();
-        } else {
-            style = style.dark_gray();
+    let mut targets = vec![
+        (devices_area, Target::DeviceList),
+        (logs_area, Target::Logs),
+    ];
+    render_devices(f, app, devices_block, devices_area, &mut targets);
+
+    let title = if app.read_only {
+        "bulbs-tui [read-only]"
+    } else {
+        "bulbs-tui"
+    };
+    let header = Paragraph::new(title).alignment(Alignment::Center);
+    f.render_widget(header, header_area);
+    render_logs(f, app, log_block, logs_area);
+    f.render_widget(Paragraph::new(help).wrap(Wrap { trim: true }), help_area);
+
+    let popup = match &app.current_widget {
+        CurrentWidget::Devices
+        | CurrentWidget::DeviceSearch
+        | CurrentWidget::Logs
+        | CurrentWidget::LogSearch
+        | CurrentWidget::Command
+        | CurrentWidget::ConfirmRemove => None,
+        CurrentWidget::DeviceSettings => render_device_settings(f, app),
+        CurrentWidget::AddDevice => render_device_adding(f, app),
+    };
+    if let Some(area) = popup {
+        targets.push((area, Target::Popup));
+    }
+    if app.show_help {
+        render_help(f, app);
+    }
+    app.targets = targets;
+}
+
+/// Renders visible devices as table scrolled to cursor, adds click targets
+/// of their rows to `targets`.
+fn render_devices(
+    f: &mut Frame,
+    app: &mut App,
+    block: Block,
+    area: Rect,
+    targets: &mut Vec<(Rect, Target)>,
+) {
+    let theme = &app.theme;
+    let columns = &app.table.columns;
+    let visible = || app.devices.bulbs.iter().filter(|d| !d.hidden);
+
+    // first column marks selected devices
+    let mut widths = vec![Constraint::Length(1)];
+    widths.extend(columns.iter().map(|c| c.width(visible(), theme)));
+
+    let mut header = vec![Cell::from("")];
+    header.extend(columns.iter().map(|&c| {
+        let marker = match (app.table.sort, app.table.reverse) {
+            (Some(s), false) if s == c => " ▲",
+            (Some(s), true) if s == c => " ▼",
+            _ => "",
+        };
+        Cell::from(format!("{}{marker}", c.title()))
+    }));
+
+    let order = app.device_order();
+    let rows: Vec<Row> = order
+        .iter()
+        .map(|&i| {
+            let dev = &app.devices.bulbs[i];
+            let style = if dev.bulb.enabled == 1 {
+                theme.on
+            } else {
+                theme.off
+            };
+            let mut row = vec![Cell::from(if dev.selected { ">" } else { " " })];
+            row.extend(columns.iter().map(|c| Cell::from(c.cell(dev, theme))));
+            Row::new(row).style(style)
+        })
+        .collect();
+
+    let mut devices_title = String::from("Devices");
+    if !app.device_filter.is_empty() {
+        _ = write!(devices_title, " [/{}]", app.device_filter);
+    }
+    let table_area = block.inner(area);
+    let devices = Table::new(rows, widths.clone())
+        .header(Row::new(header).style(theme.text.bold()))
+        .highlight_style(theme.highlight)
+        .block(block.title(devices_title));
+
+    let cursor = order.iter().position(|&i| i == app.current_device_index);
+    app.device_list.select(cursor);
+    f.render_stateful_widget(devices, area, &mut app.device_list);
+
+    // rows below header, table scrolled by offset
+    let rows_area = Rect {
+        y: table_area.y + 1,
+        height: table_area.height.saturating_sub(1),
+        ..table_area
+    };
+    let cells = Layout::horizontal(widths).spacing(1).split(table_area);
+    let offset = app.device_list.offset();
+    for (row, &i) in order.iter().skip(offset).enumerate() {
+        #[allow(clippy::cast_possible_truncation)]
+        let y = rows_area.y + row as u16;
+        if y >= rows_area.bottom() {
+            break;
         }
-        if app.current_device_index == i {
-            style = style.on_light_blue();
+        let ip = &app.devices.bulbs[i].ip;
+        targets.push((
+            Rect::new(rows_area.x, y, rows_area.width, 1),
+            Target::Device(ip.clone()),
+        ));
+        for (column, cell) in app.table.columns.iter().zip(&cells[1..]) {
+            let target = match column {
+                Column::Power => Target::Power(ip.clone()),
+                // settings are opened by clicking swatch, if it is drawn
+                Column::Color if app.theme.bulb_colors => Target::Color(ip.clone()),
+                _ => continue,
+            };
+            targets.push((Rect::new(cell.x, y, cell.width, 1), target));
         }
-        let color: Color = dev.bulb.color.parse().unwrap_or(Color::LightBlue);
-        list_items.push(ListItem::new(Line::from(vec![
-            Span::styled(dev.to_string(), style),
-            Span::styled("  ", style),
-            Span::styled("   ", style.bg(color)),
-        ])));
     }
 
-    let devices = List::new(list_items).block(devices_block.title("Devices"));
+    if order.len() > rows_area.height.into() {
+        let mut scrollbar = ScrollbarState::new(order.len()).position(cursor.unwrap_or(0));
+        f.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight),
+            area.inner(&Margin::new(0, 1)),
+            &mut scrollbar,
+        );
+    }
+}
 
-    let ll: Vec<String> = app.logs.iter().map(|l| l.replace('\n', " ")).collect();
-    let llen: u16 = app.logs.len().try_into().unwrap_or_default();
-    let paragraph_size = chunks[2].height.saturating_sub(4);
-    let scroll = if llen >= paragraph_size {
-        llen - paragraph_size
-    } else {
-        0
+/// Renders statistics and last response of current device.
+fn render_details(f: &mut Frame, app: &App, area: Rect) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(app.theme.border)
+        .title("Details");
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let Some(dev) = app.cursor().map(|i| &app.devices.bulbs[i]) else {
+        return;
     };
 
-    let logs = Paragraph::new(ll.join("\n"))
-        .block(log_block.title("Logs"))
-        .scroll((scroll.saturating_sub(2), app.log_horizontal_offset));
+    let [text_area, levels_area] =
+        Layout::vertical([Constraint::Fill(1), Constraint::Length(4)]).areas(inner);
+    let field = |label: &str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{label:8}"), app.theme.key),
+            Span::styled(value, app.theme.text),
+        ])
+    };
+    let ms = |d: Duration| format!("{} ms", d.as_millis());
+    let stats = &dev.stats;
 
-    let header = Paragraph::new("bulbs-tui").alignment(Alignment::Center);
-    f.render_widget(header, chunks[0]);
-    f.render_widget(devices, chunks[1]);
-    f.render_widget(logs, chunks[2]);
-    f.render_widget(help, chunks[3]);
+    let mut errors = stats.error_count().to_string();
+    if !stats.errors.is_empty() {
+        let kinds: Vec<String> = stats
+            .errors
+            .iter()
+            .map(|(kind, count)| format!("{} {count}", kind.as_str()))
+            .collect();
+        _ = write!(errors, " ({})", kinds.join(", "));
+    }
+    let mut lines = vec![
+        field("Name", dev.name.clone()),
+        field("Address", dev.ip.clone()),
+        field("Groups", dev.groups.join(", ")),
+        field(
+            "Seen",
+            table::last_seen(dev).unwrap_or_else(|| "never".to_string()),
+        ),
+        field(
+            "Latency",
+            match (stats.latency, stats.average_latency()) {
+                (Some(last), Some(avg)) => format!("{}, avg {}", ms(last), ms(avg)),
+                _ => "-".to_string(),
+            },
+        ),
+        field("Errors", errors),
+        Line::default(),
+        Line::styled("Last response", app.theme.key),
+    ];
+    if let Some(resp) = &stats.last_response {
+        lines.extend(
+            resp.lines()
+                .map(|l| Line::styled(l.to_string(), app.theme.text)),
+        );
+    }
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), text_area);
+
+    let levels: Vec<u64> = stats.levels.iter().copied().collect();
+    // newest levels are kept if they don't fit
+    let start = levels.len().saturating_sub(levels_area.width.into());
+    let sparkline = Sparkline::default()
+        .block(
+            Block::default()
+                .borders(Borders::TOP)
+                .border_style(app.theme.border)
+                .title("Level"),
+        )
+        .data(&levels[start..])
+        .max(100)
+        .style(app.theme.on);
+    f.render_widget(sparkline, levels_area);
+}
+
+/// Renders overlay listing key bindings of every widget.
+fn render_help(f: &mut Frame, app: &mut App) {
+    let area = centered_rect(70, 80, f.size());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(app.theme.active_border)
+        .title("Help");
+
+    let mut lines = Vec::new();
+    for ctx in keys::Context::ALL {
+        if !lines.is_empty() {
+            lines.push(Line::default());
+        }
+        lines.push(Line::styled(ctx.title(), app.theme.text.bold()));
+        for (label, keys) in app.keymap.overlay(ctx) {
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {keys:24} "), app.theme.key),
+                Span::styled(label, app.theme.text),
+            ]));
+        }
+    }
+
+    app.help_height = block.inner(area).height;
+    #[allow(clippy::cast_possible_truncation)]
+    let max_offset = (lines.len() as u16).saturating_sub(app.help_height);
+    app.help_offset = app.help_offset.min(max_offset);
+
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .scroll((app.help_offset, 0)),
+        area,
+    );
+}
 
+/// Returns prompt of focused widget, or help generated from its key bindings.
+fn help_bar<'a>(app: &App) -> Line<'a> {
+    let theme = &app.theme;
     match &app.current_widget {
-        CurrentWidget::Devices | CurrentWidget::Logs => (),
-        CurrentWidget::DeviceSettings => render_device_settings(f, app),
-        CurrentWidget::AddDevice => render_device_adding(f, app),
+        CurrentWidget::LogSearch => Line::from(vec![
+            Span::styled("/", theme.key),
+            Span::styled(app.log_search.clone(), theme.text),
+        ]),
+        CurrentWidget::DeviceSearch => Line::from(vec![
+            Span::styled("/", theme.key),
+            Span::styled(app.device_filter.clone(), theme.text),
+        ]),
+        CurrentWidget::Command => {
+            let mut spans = vec![
+                Span::styled(":", theme.key),
+                Span::styled(app.command_input.clone(), theme.text),
+            ];
+            if !app.command_hint.is_empty() {
+                spans.push(Span::styled(
+                    format!("  {}", app.command_hint.join(" ")),
+                    theme.border,
+                ));
+            }
+            Line::from(spans)
+        }
+        CurrentWidget::ConfirmRemove => {
+            let mut line = help_line(app, app.keys());
+            if let Some(dev) = app.cursor().map(|i| &app.devices.bulbs[i]) {
+                let name = if dev.name.is_empty() {
+                    dev.ip.clone()
+                } else {
+                    format!("{} ({})", dev.name, dev.ip)
+                };
+                line.spans
+                    .insert(0, Span::styled(format!("Remove {name}?"), theme.warn));
+            }
+            line
+        }
+        _ => help_line(app, app.keys()),
     }
 }
 
-fn render_device_adding(f: &mut Frame, app: &App) {
-    if let Some(adding) = &app.currently_adding {
+/// Returns help bar generated from active key bindings.
+fn help_line<'a>(app: &App, ctx: keys::Context) -> Line<'a> {
+    let mut spans = Vec::new();
+    for (i, (label, keys)) in app.keymap.help(ctx).into_iter().enumerate() {
+        let sep = if i == 0 { " " } else { " | " };
+        spans.push(Span::styled(format!("{sep}{label}: "), app.theme.text));
+        spans.push(Span::styled(keys, app.theme.key));
+    }
+    Line::from(spans)
+}
+
+fn render_logs(f: &mut Frame, app: &mut App, block: Block, area: Rect) {
+    app.log_height = area.height.saturating_sub(2).into();
+    let entries = app.visible_logs();
+    let end = app.log_end(&entries);
+    let lines: Vec<Line> = entries[end.saturating_sub(app.log_height)..end]
+        .iter()
+        .map(|e| log_line(e, &app.log_search, &app.theme))
+        .collect();
+
+    let mut log_title = String::from("Logs");
+    if app.log_level != Level::Debug {
+        _ = write!(log_title, " [{}+]", app.log_level.as_str());
+    }
+    if let Some(d) = &app.log_device {
+        _ = write!(log_title, " [{d}]");
+    }
+    if app.log_anchor.is_some() {
+        _ = write!(log_title, " [{}/{}]", end, entries.len());
+    }
+    let logs = Paragraph::new(lines)
+        .block(block.title(log_title))
+        .scroll((0, app.log_horizontal_offset));
+
+    f.render_widget(logs, area);
+}
+
+/// Returns log entry as line colored by level, with search matches highlighted.
+fn log_line<'a>(entry: &Entry, search: &str, theme: &Theme) -> Line<'a> {
+    let text = format!("{} {}", entry.time_of_day(), entry.text());
+    let style = match entry.level {
+        Level::Error => theme.error,
+        Level::Warn => theme.warn,
+        Level::Info | Level::Debug => Style::new(),
+    };
+    if search.is_empty() {
+        return Line::styled(text, style);
+    }
+
+    let haystack = text.to_ascii_lowercase();
+    let needle = search.to_ascii_lowercase();
+    let mut spans = Vec::new();
+    let mut last = 0;
+    for (i, m) in haystack.match_indices(&needle) {
+        spans.push(Span::styled(text[last..i].to_string(), style));
+        spans.push(Span::styled(
+            text[i..i + m.len()].to_string(),
+            theme.matched,
+        ));
+        last = i + m.len();
+    }
+    spans.push(Span::styled(text[last..].to_string(), style));
+    Line::from(spans)
+}
+
+/// Renders add device popup, returns its area.
+fn render_device_adding(f: &mut Frame, app: &App) -> Option<Rect> {
+    app.currently_adding.as_ref().map(|adding| {
         let popup_block = Block::default().borders(Borders::NONE);
 
         let area = centered_rect(40, 40, f.size());
@@ -128,33 +446,57 @@ fn render_device_adding(f: &mut Frame, app: &App) {
         let mut ip_block = Block::default().title("IP").borders(Borders::ALL);
         let mut name_block = Block::default().title("Name").borders(Borders::ALL);
 
-        let active_style = Style::default().bg(Color::Blue).fg(Color::Black);
+        let active_style = app.theme.input;
         match adding {
             CurrentlyAdding::IP => ip_block = ip_block.style(active_style),
             CurrentlyAdding::Name => name_block = name_block.style(active_style),
-        };
+        }
 
         f.render_widget(Clear, popup_chunks[0]);
         f.render_widget(Clear, popup_chunks[1]);
 
-        let key_text = Paragraph::new(app.ip_input.clone()).block(ip_block);
-        f.render_widget(key_text, popup_chunks[0]);
+        let theme = &app.theme;
+        let ip_focused = matches!(adding, CurrentlyAdding::IP);
+        render_input(
+            f,
+            &app.ip_input,
+            ip_block,
+            popup_chunks[0],
+            ip_focused,
+            theme,
+        );
+        render_input(
+            f,
+            &app.name_input,
+            name_block,
+            popup_chunks[1],
+            !ip_focused,
+            theme,
+        );
 
-        let value_text = Paragraph::new(app.name_input.clone()).block(name_block);
-        f.render_widget(value_text, popup_chunks[1]);
-    }
+        popup_chunks[0].union(popup_chunks[1])
+    })
 }
 
-fn render_device_settings(f: &mut Frame, app: &App) {
-    if let Some(setting) = &app.currently_setting {
+/// Renders device settings popup, returns its area.
+fn render_device_settings(f: &mut Frame, app: &App) -> Option<Rect> {
+    app.currently_setting.as_ref().map(|setting| {
         let popup_block = Block::default().borders(Borders::NONE);
 
-        let area = centered_rect(40, 40, f.size());
+        // popup needs fixed height to fit color picker
+        let frame = f.size();
+        let mut area = centered_rect(40, 40, frame);
+        area.height = SETTINGS_HEIGHT.min(frame.height);
+        area.y = frame.y + (frame.height - area.height) / 2;
         f.render_widget(popup_block, area);
 
         let popup_chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Length(3), Constraint::Length(3)])
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(PICKER_HEIGHT + 4),
+                Constraint::Length(3),
+            ])
             .split(area);
 
         let color_indicator_chunk = Layout::default()
@@ -163,34 +505,155 @@ fn render_device_settings(f: &mut Frame, app: &App) {
             .constraints([Constraint::Fill(1), Constraint::Length(6)])
             .split(popup_chunks[0])[1];
 
-        let mut color_block = Block::default().title("Color").borders(Borders::ALL);
+        let mut color_block = Block::default()
+            .title(if app.live_preview {
+                "Color [live]"
+            } else {
+                "Color"
+            })
+            .borders(Borders::ALL);
+        let mut picker_block = Block::default().borders(Borders::ALL).title(format!(
+            "{} H {:.0}° S {:.0}% V {:.0}%",
+            app.picker.to_hex(),
+            app.picker.h,
+            app.picker.s * 100.0,
+            app.picker.v * 100.0
+        ));
         let mut brightness_block = Block::default().title("Brightness").borders(Borders::ALL);
 
-        let active_style = Style::default().bg(Color::Blue).fg(Color::Black);
+        let active_style = app.theme.input;
+        // invalid color blends with background of field
         let mut color_preview: Color;
         match setting {
             CurrentlySetting::Color => {
                 color_block = color_block.style(active_style);
-                color_preview = Color::Blue;
+                color_preview = active_style.bg.unwrap_or(Color::Reset);
+            }
+            CurrentlySetting::Hue | CurrentlySetting::Shade => {
+                picker_block = picker_block.border_style(app.theme.active_border);
+                color_preview = Color::Reset;
             }
             CurrentlySetting::Brightness => {
                 brightness_block = brightness_block.style(active_style);
-                color_preview = Color::Black;
+                color_preview = Color::Reset;
             }
-        };
-        color_preview = app.color_input.parse().unwrap_or(color_preview);
+        }
+        if let Some((r, g, b)) = Hsv::from_hex(app.color_input.value()).map(Hsv::to_rgb) {
+            color_preview = Color::Rgb(r, g, b);
+        }
 
-        f.render_widget(Clear, popup_chunks[0]);
-        f.render_widget(Clear, popup_chunks[1]);
+        for chunk in popup_chunks.iter() {
+            f.render_widget(Clear, *chunk);
+        }
+
+        let theme = &app.theme;
+        let focused = matches!(setting, CurrentlySetting::Color);
+        render_input(
+            f,
+            &app.color_input,
+            color_block,
+            popup_chunks[0],
+            focused,
+            theme,
+        );
+
+        let picker_area = picker_block.inner(popup_chunks[1]);
+        let picker = Paragraph::new(picker_lines(app, setting, picker_area)).block(picker_block);
+        f.render_widget(picker, popup_chunks[1]);
+
+        let focused = matches!(setting, CurrentlySetting::Brightness);
+        let area = popup_chunks[2];
+        render_input(
+            f,
+            &app.brightness_input,
+            brightness_block,
+            area,
+            focused,
+            theme,
+        );
 
-        let color_text = Paragraph::new(app.color_input.clone()).block(color_block);
-        f.render_widget(color_text, popup_chunks[0]);
+        if app.theme.bulb_colors {
+            f.render_widget(Block::new().bg(color_preview), color_indicator_chunk);
+        }
+
+        popup_chunks[0].union(popup_chunks[2])
+    })
+}
+
+/// Renders text field scrolled to cursor, with validation error on bottom
+/// border. Terminal cursor is shown in focused field.
+fn render_input(
+    f: &mut Frame,
+    input: &Input,
+    mut block: Block,
+    area: Rect,
+    focused: bool,
+    theme: &Theme,
+) {
+    if let Some(e) = input.error() {
+        block = block.title_bottom(Span::styled(e, theme.error));
+    }
+    let inner = block.inner(area);
+    let cursor = u16::try_from(input.cursor()).unwrap_or(u16::MAX);
+    let offset = cursor.saturating_sub(inner.width.saturating_sub(1));
+    let text = Paragraph::new(input.value().to_string())
+        .block(block)
+        .scroll((0, offset));
+    f.render_widget(text, area);
+    if focused && inner.height > 0 {
+        f.set_cursor(inner.x + cursor - offset, inner.y);
+    }
+}
+
+/// Returns hue bar, separator and saturation/value grid of color picker, one
+/// cell per character. Without colors cells are drawn as characters only.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn picker_lines<'a>(app: &App, setting: &CurrentlySetting, area: Rect) -> Vec<Line<'a>> {
+    let hsv = app.picker;
+    let colors = app.theme.bulb_colors;
+    // first two columns mark focused part of picker
+    let width = area.width.saturating_sub(2).max(1);
+    let last = |n: u16| f32::from(n.saturating_sub(1).max(1));
+    let marker = |focused: bool| Span::styled(if focused { "> " } else { "  " }, app.theme.key);
+    let cell = |hsv: Hsv, cursor: bool, plain: &'static str| {
+        let (r, g, b) = hsv.to_rgb();
+        let fg = if hsv.v > 0.5 {
+            Color::Black
+        } else {
+            Color::White
+        };
+        match (colors, cursor) {
+            (true, true) => Span::styled("●", Style::new().fg(fg).bg(Color::Rgb(r, g, b))),
+            (true, false) => Span::styled(" ", Style::new().bg(Color::Rgb(r, g, b))),
+            (false, true) => Span::styled("●", app.theme.text),
+            (false, false) => Span::styled(plain, app.theme.text),
+        }
+    };
 
-        let brightness_text = Paragraph::new(app.brightness_input.clone()).block(brightness_block);
-        f.render_widget(brightness_text, popup_chunks[1]);
+    let hue_cursor = (hsv.h / 360.0 * f32::from(width)).round() as u16 % width;
+    let mut hue = vec![marker(matches!(setting, CurrentlySetting::Hue))];
+    hue.extend((0..width).map(|i| {
+        let h = f32::from(i) * 360.0 / f32::from(width);
+        cell(Hsv::new(h, 1.0, 1.0), i == hue_cursor, "-")
+    }));
+    let mut lines = vec![Line::from(hue), Line::default()];
 
-        f.render_widget(Block::new().bg(color_preview), color_indicator_chunk);
+    let s_cursor = (hsv.s * last(width)).round() as u16;
+    let v_cursor = ((1.0 - hsv.v) * last(PICKER_HEIGHT)).round() as u16;
+    for j in 0..PICKER_HEIGHT {
+        let mut row = vec![marker(j == 0 && matches!(setting, CurrentlySetting::Shade))];
+        let v = 1.0 - f32::from(j) / last(PICKER_HEIGHT);
+        row.extend((0..width).map(|i| {
+            let s = f32::from(i) / last(width);
+            cell(Hsv::new(hsv.h, s, v), i == s_cursor && j == v_cursor, "·")
+        }));
+        lines.push(Line::from(row));
     }
+    lines
 }
 
 /// helper function to create a centered rect using up certain percentage of the available rect `r`